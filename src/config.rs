@@ -0,0 +1,219 @@
+//! The user's config file, $XDG_CONFIG_HOME/rupamix/config.toml. Everything in it is optional,
+//! and flags on the command line still win over it:
+//!
+//! ```toml
+//! server = "tcp:audio-box:4713"
+//! step = "3dB"
+//! max_volume = 100
+//! max_boost = 150
+//! format = "json"
+//!
+//! [aliases.sinks]
+//! headphones = "audient"
+//! hdmi = "*hdmi-stereo*"
+//!
+//! [aliases.sources]
+//! mic = "re:^alsa_input.usb"
+//!
+//! [limits.headphones]
+//! max_volume = 80
+//! max_boost = 80
+//! ```
+//!
+//! The limits are per sink or source, by name, alias or pattern. Whatever they leave out is
+//! taken from the global ones.
+use crate::error::RupamixError;
+use crate::output::OutputFormat;
+use crate::pulse_controller::connection::ServerAddress;
+use crate::pulse_wrappers::device::{VolumeLimits, MAX_VOLUME, MAX_VOLUME_BOOSTED};
+use crate::pulse_wrappers::volume_spec::VolumeSpec;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+static CONFIG_DIR: &str = "rupamix";
+static CONFIG_NAME: &str = "config.toml";
+
+/// How much --increase and --decrease change the volume when no amount is given
+pub static DEFAULT_STEP: VolumeSpec = VolumeSpec::Percent(5.0);
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    #[serde(deserialize_with = "from_str")]
+    pub server: Option<ServerAddress>,
+    #[serde(deserialize_with = "from_str")]
+    pub step: Option<VolumeSpec>,
+    pub max_volume: Option<f64>,
+    pub max_boost: Option<f64>,
+    pub relative_to_base: Option<bool>,
+    #[serde(deserialize_with = "from_str")]
+    pub format: Option<OutputFormat>,
+    pub aliases: Aliases,
+    pub limits: HashMap<String, DeviceLimits>,
+}
+
+/// Short names for sinks and sources. The targets can be anything --name takes,
+/// i.e. a full name or a pattern.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Aliases {
+    pub sinks: HashMap<String, String>,
+    pub sources: HashMap<String, String>,
+}
+
+/// How loud a single sink or source may get, see VolumeLimits
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceLimits {
+    pub max_volume: Option<f64>,
+    pub max_boost: Option<f64>,
+    pub relative_to_base: Option<bool>,
+}
+
+impl DeviceLimits {
+    /// These limits, with whatever they leave out taken from global
+    pub fn over(&self, global: VolumeLimits) -> VolumeLimits {
+        VolumeLimits::new(
+            self.max_volume.unwrap_or(global.max()),
+            self.max_boost.unwrap_or(global.max_boosted()),
+            self.relative_to_base.unwrap_or(global.relative_to_base()),
+        )
+    }
+}
+
+impl Config {
+    /// The config at the default path, or the built in defaults if there isn't one
+    pub fn load() -> Result<Config, RupamixError> {
+        match config_path() {
+            Some(path) => Config::load_from(&path),
+            None => Ok(Config::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> Result<Config, RupamixError> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Config::parse(&contents)
+                .map_err(|e| RupamixError::Config(format!("{}: {e}", path.display()))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn parse(contents: &str) -> Result<Config, String> {
+        toml::from_str(contents).map_err(|e| e.message().to_string())
+    }
+
+    pub fn step(&self) -> VolumeSpec {
+        self.step.unwrap_or(DEFAULT_STEP)
+    }
+
+    /// The step spelled the way the command line takes it. Display rounds, this doesn't.
+    pub fn step_arg(&self) -> String {
+        match self.step() {
+            VolumeSpec::Percent(pct) => pct.to_string(),
+            VolumeSpec::Decibel(db) => format!("{db}dB"),
+            VolumeSpec::Linear(factor) => format!("{factor}x"),
+            VolumeSpec::Raw(raw) => format!("{raw}raw"),
+        }
+    }
+
+    pub fn max_volume(&self) -> f64 {
+        self.max_volume.unwrap_or(f64::from(MAX_VOLUME))
+    }
+
+    pub fn max_boost(&self) -> f64 {
+        self.max_boost.unwrap_or(f64::from(MAX_VOLUME_BOOSTED))
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format.unwrap_or_default()
+    }
+}
+
+/// $XDG_CONFIG_HOME/rupamix/config.toml, or ~/.config/rupamix/config.toml without it
+pub fn config_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(dir.join(CONFIG_DIR).join(CONFIG_NAME))
+}
+
+/// Lets the config use the same spellings as the command line, i.e. step = "3dB"
+fn from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = String::deserialize(deserializer)?;
+    value.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = Config::parse(
+            r#"
+            server = "unix:/run/pulse/native"
+            step = "3dB"
+            max_boost = 150
+            format = "json"
+
+            [aliases.sinks]
+            headphones = "audient"
+
+            [aliases.sources]
+            mic = "re:^alsa_input.usb"
+
+            [limits.headphones]
+            max_volume = 80
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            Some("unix:/run/pulse/native".parse().unwrap()),
+            config.server
+        );
+        assert_eq!(VolumeSpec::Decibel(3.0), config.step());
+        assert_eq!(Ok(config.step()), config.step_arg().parse());
+        assert_eq!(150.0, config.max_boost());
+        assert_eq!(f64::from(MAX_VOLUME), config.max_volume());
+        assert_eq!(OutputFormat::Json, config.format());
+        assert_eq!("audient", config.aliases.sinks["headphones"]);
+        assert_eq!("re:^alsa_input.usb", config.aliases.sources["mic"]);
+
+        let global = VolumeLimits::new(90.0, 150.0, true);
+        assert_eq!(
+            VolumeLimits::new(80.0, 150.0, true),
+            config.limits["headphones"].over(global)
+        );
+    }
+
+    #[test]
+    fn test_empty_config_is_the_defaults() {
+        let config = Config::parse("").unwrap();
+
+        assert_eq!(DEFAULT_STEP, config.step());
+        assert_eq!(OutputFormat::Text, config.format());
+        assert!(config.aliases.sinks.is_empty());
+    }
+
+    #[test]
+    fn test_bad_values_are_reported() {
+        assert!(Config::parse(r#"step = "loud""#).is_err());
+        assert!(Config::parse(r#"volume = 100"#).is_err());
+        assert!(Config::parse(r#"server = "audio-box""#).is_err());
+        assert!(Config::parse("[limits.hdmi]\nmax = 80").is_err());
+    }
+}
@@ -0,0 +1,386 @@
+//! A long running process that keeps one connection to Pulse open, so that commands from
+//! keybindings don't pay for connecting and syncing every time. The CLI hands its arguments
+//! to the daemon over a Unix socket in $XDG_RUNTIME_DIR and gets back whether they worked,
+//! along with anything they printed. The daemon keeps its state current with subscribe
+//! events instead of re-syncing.
+use crate::error::RupamixError;
+use crate::pulse_controller::Pulse;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+static SOCKET_NAME: &str = "rupamix.sock";
+
+/// How long a client gets to send its request, and to take the reply. A slow client doesn't
+/// hold anyone else up while it is sending, this only keeps it from staying around forever.
+static CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Reply {
+    /// What the command printed, the client prints it on its side
+    #[serde(default)]
+    output: String,
+    error: Option<String>,
+    /// The exit code the command would have had if it was run in the CLI
+    code: u8,
+}
+
+/// The socket lives in $XDG_RUNTIME_DIR, there is no daemon without one
+pub fn socket_path() -> Option<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR").map(|dir| PathBuf::from(dir).join(SOCKET_NAME))
+}
+
+/// Sends args to a running daemon. None means there is no daemon to send them to,
+/// and the command should be run here instead.
+pub fn forward(args: &[String]) -> Option<Result<(), RupamixError>> {
+    let stream = UnixStream::connect(socket_path()?).ok()?;
+    Some(send(stream, args, &mut io::stdout()).unwrap_or_else(|e| Err(RupamixError::Io(e))))
+}
+
+/// Sends args over stream and writes what the command printed to out
+fn send(
+    mut stream: UnixStream,
+    args: &[String],
+    out: &mut dyn Write,
+) -> io::Result<Result<(), RupamixError>> {
+    writeln!(stream, "{}", serde_json::to_string(args)?)?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    let reply: Reply = serde_json::from_str(&line)?;
+    out.write_all(reply.output.as_bytes())?;
+
+    Ok(match reply.error {
+        Some(message) => Err(RupamixError::Forwarded {
+            message,
+            code: reply.code,
+        }),
+        None => Ok(()),
+    })
+}
+
+/// Listens on the socket until something goes wrong, running each client's args with handle.
+/// Whatever handle writes is sent back to the client. A command that fails is reported back
+/// with its error, and the state is synced again since we can't know how far it got.
+pub fn serve<F>(pulse: &mut Pulse, handle: F) -> Result<(), RupamixError>
+where
+    F: FnMut(&mut Pulse, Vec<String>, &mut dyn Write) -> Result<(), RupamixError>,
+{
+    let path = socket_path()
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set"))?;
+
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            return Err(RupamixError::Usage(
+                "The daemon is already running".to_string(),
+            ));
+        }
+        // Left behind by a daemon that was killed
+        std::fs::remove_file(&path)?;
+    }
+
+    serve_on(UnixListener::bind(&path)?, pulse, handle)
+}
+
+/// serve, on a socket that is already bound. The mainloop watches the socket and the clients
+/// along with the server, so we sleep in it until there is a client, a request or an event.
+/// Clients that leave mid-request or take too long are dropped without a word, there is
+/// nobody to tell.
+fn serve_on<F>(listener: UnixListener, pulse: &mut Pulse, mut handle: F) -> Result<(), RupamixError>
+where
+    F: FnMut(&mut Pulse, Vec<String>, &mut dyn Write) -> Result<(), RupamixError>,
+{
+    listener.set_nonblocking(true)?;
+    pulse.subscribe()?;
+    pulse.watch_fd(listener.as_raw_fd())?;
+    let mut clients: Vec<Client> = Vec::new();
+
+    loop {
+        loop {
+            let stream = match listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(e.into()),
+            };
+            stream.set_nonblocking(true)?;
+            pulse.watch_fd(stream.as_raw_fd())?;
+            clients.push(Client::new(stream));
+        }
+
+        let mut waiting = Vec::new();
+        for mut client in clients.drain(..) {
+            match client.read_request() {
+                Ok(None) if client.connected.elapsed() < CLIENT_TIMEOUT => {
+                    waiting.push(client);
+                    continue;
+                }
+                Ok(Some(request)) => {
+                    let _ = reply(&client.stream, &request, pulse, &mut |pulse, args, out| {
+                        let res = handle(pulse, args, out);
+                        if res.is_err() {
+                            pulse.update()?;
+                        }
+                        res
+                    });
+                }
+                Ok(None) | Err(_) => (),
+            }
+            pulse.unwatch_fd(client.stream.as_raw_fd());
+        }
+        clients = waiting;
+
+        // Running the clients' commands may have queued events as well
+        pulse.process_events(|_, _| {})?;
+        pulse.iterate(true)?;
+    }
+}
+
+/// A client whose request may not have fully arrived yet
+struct Client {
+    stream: UnixStream,
+    request: Vec<u8>,
+    connected: Instant,
+}
+
+impl Client {
+    fn new(stream: UnixStream) -> Client {
+        Client {
+            stream,
+            request: Vec::new(),
+            connected: Instant::now(),
+        }
+    }
+
+    /// Reads whatever has arrived so far, the request is there once its line is complete.
+    /// A client that went away before that has nobody to run it for.
+    fn read_request(&mut self) -> io::Result<Option<String>> {
+        let mut buf = [0; 1024];
+
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        ErrorKind::UnexpectedEof,
+                        "The client left mid-request",
+                    ))
+                }
+                Ok(read) => self.request.extend_from_slice(&buf[..read]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(None),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+
+            if let Some(end) = self.request.iter().position(|&byte| byte == b'\n') {
+                return Ok(Some(
+                    String::from_utf8_lossy(&self.request[..end]).into_owned(),
+                ));
+            }
+        }
+    }
+}
+
+/// Runs the request and sends back the reply, one request per client
+fn reply<S, F>(stream: &UnixStream, request: &str, state: &mut S, handle: &mut F) -> io::Result<()>
+where
+    F: FnMut(&mut S, Vec<String>, &mut dyn Write) -> Result<(), RupamixError>,
+{
+    let mut output = Vec::new();
+    let res = match serde_json::from_str::<Vec<String>>(request) {
+        Ok(args) => handle(state, args, &mut output),
+        Err(e) => Err(RupamixError::Usage(format!("Bad request: {e}"))),
+    };
+    let output = String::from_utf8_lossy(&output).into_owned();
+    let reply = match res {
+        Ok(()) => Reply {
+            output,
+            error: None,
+            code: 0,
+        },
+        Err(e) => Reply {
+            output,
+            error: Some(e.to_string()),
+            code: e.exit_code(),
+        },
+    };
+
+    stream.set_nonblocking(false)?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+    let mut stream = stream;
+    writeln!(stream, "{}", serde_json::to_string(&reply)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pulse_controller::fake_backend::FakeBackend;
+    use std::process;
+    use std::thread::{self, JoinHandle};
+
+    fn round_trip(args: &[&str]) -> (Vec<String>, Result<(), RupamixError>) {
+        let (client, server) = UnixStream::pair().unwrap();
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+
+        let server = thread::spawn(move || {
+            // Blocking, so the request is there in one read_request
+            let request = Client::new(server.try_clone().unwrap())
+                .read_request()
+                .unwrap()
+                .unwrap();
+            let mut received = Vec::new();
+            reply(
+                &server,
+                &request,
+                &mut received,
+                &mut |received: &mut Vec<String>, args, _: &mut dyn Write| {
+                    *received = args;
+                    if received.iter().any(|arg| arg == "fail") {
+                        Err(RupamixError::Operation("failed".to_string()))
+                    } else {
+                        Ok(())
+                    }
+                },
+            )
+            .unwrap();
+            received
+        });
+
+        let res = send(client, &args, &mut io::sink()).unwrap();
+        (server.join().unwrap(), res)
+    }
+
+    /// A daemon on a fake desktop, on a socket of its own in the temp dir. It knows three
+    /// commands: mute, status, and stop, which takes the fake server away and so ends serve.
+    fn fake_daemon(name: &str) -> (PathBuf, JoinHandle<Result<(), RupamixError>>) {
+        let path = env::temp_dir().join(format!("rupamix-{}-{name}.sock", process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        let daemon = thread::spawn(move || {
+            let backend = FakeBackend::desktop();
+            let mut pulse = Pulse::with_backend(Box::new(backend.clone()))?;
+
+            serve_on(listener, &mut pulse, |pulse, args, out| {
+                match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+                    ["mute"] => pulse.set_sink_mute(true, None, None),
+                    ["status"] => {
+                        let status = pulse.sink_status(None, None)?;
+                        writeln!(out, "{} {}", status.volume, status.muted)?;
+                        Ok(())
+                    }
+                    ["stop"] => {
+                        backend.disconnect();
+                        Ok(())
+                    }
+                    _ => Err(RupamixError::Usage(format!(
+                        "Unknown command: {}",
+                        args.join(" ")
+                    ))),
+                }
+            })
+        });
+
+        (path, daemon)
+    }
+
+    /// Runs args on the daemon at path, returning what it printed
+    fn request(path: &PathBuf, args: &[&str]) -> (String, Result<(), RupamixError>) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut out = Vec::new();
+
+        let res = send(UnixStream::connect(path).unwrap(), &args, &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), res)
+    }
+
+    /// Stops the daemon, which only ends because the server went away
+    fn stop(path: PathBuf, daemon: JoinHandle<Result<(), RupamixError>>) {
+        assert!(request(&path, &["stop"]).1.is_ok());
+
+        let res = daemon.join().unwrap();
+        assert!(matches!(res, Err(RupamixError::Connection(_))));
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_args_reach_the_handler() {
+        let (received, res) = round_trip(&["rupamix", "volume", "--increase"]);
+
+        assert_eq!(vec!["rupamix", "volume", "--increase"], received);
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn test_errors_come_back() {
+        let (_, res) = round_trip(&["rupamix", "fail"]);
+
+        let e = res.unwrap_err();
+
+        assert_eq!("Operation Error: failed", e.to_string());
+        assert_eq!(4, e.exit_code());
+    }
+
+    #[test]
+    fn test_forwarded_commands_change_the_daemons_state() {
+        let (path, daemon) = fake_daemon("forward");
+
+        let (out, res) = request(&path, &["mute"]);
+        assert!(res.is_ok());
+        assert!(out.is_empty());
+        assert_eq!("50 true\n", request(&path, &["status"]).0);
+
+        stop(path, daemon);
+    }
+
+    #[test]
+    fn test_unknown_commands_are_usage_errors() {
+        let (path, daemon) = fake_daemon("unknown");
+
+        let (out, res) = request(&path, &["louder"]);
+
+        let e = res.unwrap_err();
+        assert!(out.is_empty());
+        assert_eq!("Usage Error: Unknown command: louder", e.to_string());
+        assert_eq!(2, e.exit_code());
+
+        stop(path, daemon);
+    }
+
+    #[test]
+    fn test_a_slow_client_holds_nobody_up() {
+        let (path, daemon) = fake_daemon("slow");
+
+        let mut slow = UnixStream::connect(&path).unwrap();
+        slow.write_all(b"[\"mute\"").unwrap();
+
+        let start = Instant::now();
+        assert_eq!("50 false\n", request(&path, &["status"]).0);
+        assert!(start.elapsed() < CLIENT_TIMEOUT);
+
+        // Once the rest of its request is there it is run like any other
+        slow.write_all(b"]\n").unwrap();
+        let mut line = String::new();
+        BufReader::new(slow).read_line(&mut line).unwrap();
+        let reply: Reply = serde_json::from_str(&line).unwrap();
+        assert_eq!(None, reply.error);
+        assert_eq!("50 true\n", request(&path, &["status"]).0);
+
+        stop(path, daemon);
+    }
+
+    #[test]
+    fn test_a_client_leaving_mid_request_is_ignored() {
+        let (path, daemon) = fake_daemon("leaving");
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        client.write_all(b"[\"mute\"").unwrap();
+        drop(client);
+
+        // The half sent mute was never run, and the daemon is still there to say so
+        assert_eq!("50 false\n", request(&path, &["status"]).0);
+
+        stop(path, daemon);
+    }
+}
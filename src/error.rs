@@ -0,0 +1,124 @@
+//! Everything that can go wrong in rupamix, and the exit code each kind of failure maps to:
+//!
+//! | Code | Error      | Meaning                                                    |
+//! |------|------------|------------------------------------------------------------|
+//! | 0    |            | Success                                                    |
+//! | 2    | Usage      | The command line doesn't make sense (clap uses 2 as well)  |
+//! | 3    | Connection | Unable to connect to the Pulse Audio server                |
+//! | 4    | Operation  | The server, or our mainloop, failed an operation           |
+//! | 5    | Lookup     | No such device, stream or channel                          |
+//! | 6    | Io         | Reading or writing failed, i.e. the daemon socket          |
+//! | 7    | Config     | The config file doesn't parse                              |
+//! | 8    | Timeout    | The server didn't answer in time                           |
+//!
+//! Errors passed back from the daemon keep the code they had there.
+use crate::output::OutputError;
+use crate::pulse_controller::device_manager::DeviceError;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum RupamixError {
+    Usage(String),
+    Connection(String),
+    Operation(String),
+    Lookup(DeviceError),
+    Io(io::Error),
+    Config(String),
+    /// Connecting or an operation took longer than we were willing to wait
+    Timeout(String),
+    /// An error the daemon ran into while running a forwarded command
+    Forwarded {
+        message: String,
+        code: u8,
+    },
+}
+
+impl RupamixError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            RupamixError::Usage(_) => 2,
+            RupamixError::Connection(_) => 3,
+            RupamixError::Operation(_) => 4,
+            RupamixError::Lookup(_) => 5,
+            RupamixError::Io(_) => 6,
+            RupamixError::Config(_) => 7,
+            RupamixError::Timeout(_) => 8,
+            RupamixError::Forwarded { code, .. } => *code,
+        }
+    }
+}
+
+impl fmt::Display for RupamixError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RupamixError::Usage(e) => write!(f, "Usage Error: {e}"),
+            RupamixError::Connection(e) => write!(f, "Connection Error: {e}"),
+            RupamixError::Operation(e) => write!(f, "Operation Error: {e}"),
+            RupamixError::Lookup(e) => write!(f, "{e}"),
+            RupamixError::Io(e) => write!(f, "IO Error: {e}"),
+            RupamixError::Config(e) => write!(f, "Config Error: {e}"),
+            RupamixError::Timeout(e) => write!(f, "Timeout Error: {e}"),
+            RupamixError::Forwarded { message, .. } => write!(f, "{message}"),
+        }
+    }
+}
+
+impl std::error::Error for RupamixError {}
+
+impl From<DeviceError> for RupamixError {
+    fn from(e: DeviceError) -> Self {
+        RupamixError::Lookup(e)
+    }
+}
+
+impl From<io::Error> for RupamixError {
+    fn from(e: io::Error) -> Self {
+        RupamixError::Io(e)
+    }
+}
+
+impl From<OutputError> for RupamixError {
+    fn from(e: OutputError) -> Self {
+        match e {
+            OutputError::NotStructured => RupamixError::Usage(e.to_string()),
+            OutputError::Serialize(_) => {
+                RupamixError::Io(io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let errors = [
+            RupamixError::Usage(String::new()),
+            RupamixError::Connection(String::new()),
+            RupamixError::Operation(String::new()),
+            RupamixError::Lookup(DeviceError::NoSinks(String::new())),
+            RupamixError::Io(io::Error::other("")),
+            RupamixError::Config(String::new()),
+            RupamixError::Timeout(String::new()),
+        ];
+        let mut codes: Vec<u8> = errors.iter().map(RupamixError::exit_code).collect();
+        codes.sort();
+        codes.dedup();
+
+        assert_eq!(errors.len(), codes.len());
+        assert!(!codes.contains(&0));
+    }
+
+    #[test]
+    fn test_forwarded_keeps_its_code() {
+        let e = RupamixError::Forwarded {
+            message: "gone".to_string(),
+            code: 5,
+        };
+
+        assert_eq!(5, e.exit_code());
+    }
+}
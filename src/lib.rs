@@ -1,5 +1,10 @@
+pub mod config;
+pub mod daemon;
+pub mod error;
+pub mod output;
 pub mod pulse_controller;
 pub mod pulse_wrappers;
+pub mod status;
 
 #[cfg(feature = "extractor")]
 pub mod info_xtractor;
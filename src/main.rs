@@ -1,5 +1,18 @@
-use clap::{Parser, Subcommand};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command, CommandFactory, FromArgMatches, Parser, Subcommand};
+use pulse::channelmap::Position;
+use rupamix::config::Config;
+use rupamix::daemon;
+use rupamix::error::RupamixError;
+use rupamix::output::{OutputFormat, Report};
+use rupamix::pulse_controller::connection::{ConnectOptions, ServerAddress};
 use rupamix::pulse_controller::Pulse;
+use rupamix::pulse_wrappers::device::{VolumeLimits, MAX_VOLUME, MAX_VOLUME_BOOSTED};
+use rupamix::pulse_wrappers::volume_spec::{VolumeSpec, VolumeUnit};
+use rupamix::status::{StatusFormat, DEFAULT_TEMPLATE};
+use std::io::{self, Write};
+use std::process::ExitCode;
+use std::time::Duration;
 
 #[cfg(feature = "extractor")]
 use rupamix::info_xtractor::InfoXtractor;
@@ -15,15 +28,77 @@ struct Cli {
 
     #[arg(short, long)]
     #[arg(visible_alias = "index")]
-    #[arg(help = "The index of the sink; uses default sink if not specified")]
+    #[arg(help = "The index of the sink (source for recording); uses default if not specified")]
     #[arg(conflicts_with = "name")]
     index: Option<u32>,
 
     #[arg(short, long)]
-    #[arg(help = "The name of the sink; uses default sink if not specified")]
+    #[arg(
+        help = "The name of the sink (source for recording), or a substring, glob or re: regex \
+                of its name or description; uses default if not specified"
+    )]
     #[arg(conflicts_with = "index")]
     name: Option<String>,
 
+    #[arg(long)]
+    #[arg(global = true)]
+    #[arg(default_value_t = f64::from(MAX_VOLUME))]
+    #[arg(help = "The highest volume allowed without --boost, in percent")]
+    max_volume: f64,
+
+    #[arg(long)]
+    #[arg(global = true)]
+    #[arg(default_value_t = f64::from(MAX_VOLUME_BOOSTED))]
+    #[arg(help = "The highest volume allowed with --boost, in percent")]
+    max_boost: f64,
+
+    #[arg(long)]
+    #[arg(global = true)]
+    #[arg(help = "Take the volume limits as percentages of the device's base volume")]
+    relative_to_base: bool,
+
+    #[arg(long)]
+    #[arg(global = true)]
+    #[arg(default_value = "text")]
+    #[arg(help = "How print and list output is written: text, json, yaml or toml")]
+    format: OutputFormat,
+
+    #[arg(long)]
+    #[arg(global = true)]
+    #[arg(help = "Run the command here even if the daemon is running")]
+    no_daemon: bool,
+
+    #[arg(long)]
+    #[arg(global = true)]
+    #[arg(env = "PULSE_SERVER")]
+    #[arg(help = "The server to connect to, i.e. unix:/path/to/native or tcp:host:4713")]
+    server: Option<ServerAddress>,
+
+    #[arg(long)]
+    #[arg(global = true)]
+    #[arg(help = "Don't start a server if there is none running")]
+    no_autospawn: bool,
+
+    #[arg(long)]
+    #[arg(global = true)]
+    #[arg(default_value = "5")]
+    #[arg(value_parser = parse_seconds)]
+    #[arg(help = "Give up connecting after this many seconds")]
+    connect_timeout: Duration,
+
+    #[arg(long)]
+    #[arg(global = true)]
+    #[arg(default_value = "10")]
+    #[arg(value_parser = parse_seconds)]
+    #[arg(help = "Give up on a request the server hasn't answered after this many seconds")]
+    operation_timeout: Duration,
+
+    #[arg(long)]
+    #[arg(global = true)]
+    #[arg(default_value_t = 0)]
+    #[arg(help = "Try connecting again this many times if the server isn't there")]
+    retries: u32,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -33,35 +108,132 @@ enum Commands {
     #[command(visible_aliases = ["vol", "v"])]
     #[command(about = "Volume control, use volume --help for more info")]
     Volume {
+        #[arg(long)]
+        #[arg(help = "Control a source (microphone) instead of a sink")]
+        source: bool,
+
+        #[arg(short, long)]
+        #[arg(value_parser = parse_position)]
+        #[arg(conflicts_with_all = ["toggle_mute", "mute", "unmute", "balance", "fade"])]
+        #[arg(help = "Only change the channel at this position, i.e. front-left or lfe")]
+        channel: Option<Position>,
+
+        #[arg(long)]
+        #[arg(value_parser = parse_balance)]
+        #[arg(allow_negative_numbers = true)]
+        #[arg(conflicts_with_all = ["increase", "decrease", "toggle_mute", "mute", "unmute", "set", "fade"])]
+        #[arg(
+            help = "Sets the balance from -1.0 (left) to 1.0 (right), keeping the overall level"
+        )]
+        balance: Option<f32>,
+
+        #[arg(long)]
+        #[arg(value_parser = parse_balance)]
+        #[arg(allow_negative_numbers = true)]
+        #[arg(conflicts_with_all = ["increase", "decrease", "toggle_mute", "mute", "unmute", "set", "balance"])]
+        #[arg(help = "Sets the fade from -1.0 (rear) to 1.0 (front), keeping the overall level")]
+        fade: Option<f32>,
+
         #[arg(short, long)]
-        #[arg(help = "Allow volume to go past 100; hard capped at 120 currently")]
+        #[arg(help = "Allow volume to go past --max-volume, up to --max-boost")]
         boost: bool,
 
         #[arg(short, long)]
-        #[arg(conflicts_with_all =  ["decrease", "toggle_mute", "set"])]
+        #[arg(conflicts_with_all =  ["decrease", "toggle_mute", "mute", "unmute", "set"])]
         #[arg(default_value = "0")]
         #[arg(num_args = 0..=1)]
         #[arg(default_missing_value = "5")]
         #[arg(help = "Increase volume by the specified amount, or default if not specified")]
-        increase: u8,
+        #[arg(
+            long_help = "Increase volume by the specified amount, or default if not specified.\nAmounts are percent by default, but 3dB, 1.5x or 1000raw also work"
+        )]
+        increase: VolumeSpec,
 
         #[arg(short, long)]
-        #[arg(conflicts_with_all =  ["increase", "toggle_mute", "set"])]
+        #[arg(conflicts_with_all =  ["increase", "toggle_mute", "mute", "unmute", "set"])]
         #[arg(default_value = "0")]
         #[arg(num_args = 0..=1)]
         #[arg(default_missing_value = "5")]
         #[arg(help = "Decrease volume by the specified amount, or default if not specified")]
-        decrease: u8,
+        #[arg(
+            long_help = "Decrease volume by the specified amount, or default if not specified.\nAmounts are percent by default, but 3dB, 1.5x or 1000raw also work"
+        )]
+        decrease: VolumeSpec,
 
         #[arg(short, long)]
-        #[arg(conflicts_with_all =  ["increase", "decrease", "set"])]
+        #[arg(conflicts_with_all =  ["increase", "decrease", "mute", "unmute", "set"])]
         #[arg(help = "Mutes if not muted, unmutes if muted")]
         toggle_mute: bool,
 
         #[arg(short, long)]
-        #[arg(conflicts_with_all =  ["increase", "decrease", "toggle_mute"])]
-        #[arg(help = "Sets the volume to the specified value")]
-        set: Option<u8>,
+        #[arg(conflicts_with_all =  ["increase", "decrease", "toggle_mute", "unmute", "set"])]
+        #[arg(help = "Mutes the device, the volume is kept for when it is unmuted")]
+        mute: bool,
+
+        #[arg(short, long)]
+        #[arg(conflicts_with_all =  ["increase", "decrease", "toggle_mute", "mute", "set"])]
+        #[arg(help = "Unmutes the device")]
+        unmute: bool,
+
+        #[arg(short, long)]
+        #[arg(conflicts_with_all =  ["increase", "decrease", "toggle_mute", "mute", "unmute"])]
+        #[arg(allow_hyphen_values = true)]
+        #[arg(
+            help = "Sets the volume to the specified value, i.e. 50, 50%, -6dB, 0.5x or 32768raw"
+        )]
+        set: Option<VolumeSpec>,
+    },
+
+    #[command(visible_alias = "s")]
+    #[command(about = "Per-application playback streams, use stream --help for more info")]
+    Stream {
+        #[command(subcommand)]
+        command: StreamCommands,
+    },
+
+    #[command(visible_alias = "rec")]
+    #[command(about = "Per-application recording streams, use recording --help for more info")]
+    Recording {
+        #[command(subcommand)]
+        command: StreamCommands,
+    },
+
+    #[command(about = "Shows everything known about a sink or source")]
+    Info {
+        #[arg(long)]
+        #[arg(help = "Show a source (microphone) instead of a sink")]
+        source: bool,
+
+        #[arg(help = "The index or name of the device, or --index/--name, or the default")]
+        device: Option<String>,
+
+        #[arg(short, long)]
+        #[arg(default_value = "pct")]
+        #[arg(help = "Show volumes in pct, db, linear or raw")]
+        unit: VolumeUnit,
+    },
+
+    #[command(about = "Switches the port of a sink or source, i.e. speakers or headphones")]
+    Port {
+        #[arg(long)]
+        #[arg(help = "Switch a source (microphone) instead of a sink")]
+        source: bool,
+
+        #[arg(help = "The name of the port, or a pattern matching its name or description")]
+        port: String,
+    },
+
+    #[command(about = "Sound card profiles, use card --help for more info")]
+    Card {
+        #[command(subcommand)]
+        command: CardCommands,
+    },
+
+    #[command(visible_alias = "d")]
+    #[command(about = "Sets the default sink or source, use default --help for more info")]
+    Default {
+        #[command(subcommand)]
+        command: DefaultCommands,
     },
 
     #[command(visible_alias = "p")]
@@ -78,8 +250,62 @@ enum Commands {
         #[arg(short, long)]
         #[arg(help = "Prints the volume of the specifed sink or the default if not specified")]
         volume: bool,
+
+        #[arg(long)]
+        #[arg(help = "Prints the volume of the specifed source or the default if not specified")]
+        source_volume: bool,
+
+        #[arg(short, long)]
+        #[arg(help = "Prints the volume of every channel of the specified sink")]
+        channels: bool,
+
+        #[arg(long)]
+        #[arg(help = "Prints the volume of every channel of the specified source")]
+        source_channels: bool,
+
+        #[arg(long)]
+        #[arg(default_value = "pct")]
+        #[arg(help = "The unit volumes are printed in: pct, db, linear or raw")]
+        unit: VolumeUnit,
     },
 
+    #[command(about = "A one line summary of the default sink for status bars")]
+    Status {
+        #[arg(long)]
+        #[arg(help = "Show a source (microphone) instead of a sink")]
+        source: bool,
+
+        #[arg(short, long)]
+        #[arg(default_value = "plain")]
+        #[arg(help = "The bar to format for: plain, waybar, i3blocks or polybar")]
+        bar: StatusFormat,
+
+        #[arg(short, long)]
+        #[arg(default_value = DEFAULT_TEMPLATE)]
+        #[arg(help = "Fills in {icon}, {volume}, {muted}, {name} and {description}")]
+        template: String,
+    },
+
+    #[command(visible_alias = "w")]
+    #[command(about = "Prints a line, or a JSON object, each time something changes")]
+    Watch {
+        #[arg(long)]
+        #[arg(help = "With --bar, show a source (microphone) instead of a sink")]
+        source: bool,
+
+        #[arg(short, long)]
+        #[arg(help = "Print a status line for this bar instead, whenever it changes")]
+        bar: Option<StatusFormat>,
+
+        #[arg(short, long)]
+        #[arg(default_value = DEFAULT_TEMPLATE)]
+        #[arg(help = "The status template used with --bar, see status --help")]
+        template: String,
+    },
+
+    #[command(about = "Keeps a connection to Pulse open so other commands can skip connecting")]
+    Daemon,
+
     #[command(visible_alias = "x")]
     #[command(
         about = "Gets system info about volumes, really only useful if you are developing this tool"
@@ -94,48 +320,822 @@ enum Commands {
     },
 }
 
-fn main() -> Result<(), &'static str> {
-    let cli = Cli::parse();
+#[derive(Debug, Subcommand)]
+enum CardCommands {
+    #[command(visible_alias = "ls")]
+    #[command(about = "Lists the cards and their profiles, the active one is marked with a *")]
+    List,
 
-    let mut pulse = Pulse::new();
+    #[command(about = "Switches a card to another profile, i.e. A2DP or HSP/HFP")]
+    Profile {
+        #[arg(help = "The index or name of the card, or a pattern like for --name")]
+        card: String,
+
+        #[arg(help = "The name of the profile, or a pattern matching its name or description")]
+        profile: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum StreamCommands {
+    #[command(visible_alias = "ls")]
+    #[command(about = "Lists the streams and the device each one is using")]
+    List,
+
+    #[command(visible_aliases = ["vol", "v"])]
+    #[command(about = "Volume control for the selected streams")]
+    Volume {
+        #[arg(long)]
+        #[arg(help = "The index of the stream")]
+        #[arg(conflicts_with_all = ["app", "binary"])]
+        stream: Option<u32>,
+
+        #[arg(short, long)]
+        #[arg(help = "The application name of the streams, i.e. Firefox")]
+        #[arg(conflicts_with_all = ["stream", "binary"])]
+        app: Option<String>,
+
+        #[arg(long)]
+        #[arg(help = "The process binary of the streams, i.e. firefox")]
+        #[arg(conflicts_with_all = ["stream", "app"])]
+        binary: Option<String>,
+
+        #[arg(short, long)]
+        #[arg(help = "Allow volume to go past --max-volume, up to --max-boost")]
+        boost: bool,
+
+        #[arg(short, long)]
+        #[arg(conflicts_with_all =  ["decrease", "toggle_mute", "mute", "unmute", "set"])]
+        #[arg(default_value = "0")]
+        #[arg(num_args = 0..=1)]
+        #[arg(default_missing_value = "5")]
+        #[arg(help = "Increase volume by the specified amount, or default if not specified")]
+        #[arg(
+            long_help = "Increase volume by the specified amount, or default if not specified.\nAmounts are percent by default, but 3dB, 1.5x or 1000raw also work"
+        )]
+        increase: VolumeSpec,
+
+        #[arg(short, long)]
+        #[arg(conflicts_with_all =  ["increase", "toggle_mute", "mute", "unmute", "set"])]
+        #[arg(default_value = "0")]
+        #[arg(num_args = 0..=1)]
+        #[arg(default_missing_value = "5")]
+        #[arg(help = "Decrease volume by the specified amount, or default if not specified")]
+        #[arg(
+            long_help = "Decrease volume by the specified amount, or default if not specified.\nAmounts are percent by default, but 3dB, 1.5x or 1000raw also work"
+        )]
+        decrease: VolumeSpec,
+
+        #[arg(short, long)]
+        #[arg(conflicts_with_all =  ["increase", "decrease", "mute", "unmute", "set"])]
+        #[arg(help = "Mutes if not muted, unmutes if muted")]
+        toggle_mute: bool,
+
+        #[arg(short, long)]
+        #[arg(conflicts_with_all =  ["increase", "decrease", "toggle_mute", "unmute", "set"])]
+        #[arg(help = "Mutes the streams")]
+        mute: bool,
+
+        #[arg(short, long)]
+        #[arg(conflicts_with_all =  ["increase", "decrease", "toggle_mute", "mute", "set"])]
+        #[arg(help = "Unmutes the streams")]
+        unmute: bool,
+
+        #[arg(short, long)]
+        #[arg(conflicts_with_all =  ["increase", "decrease", "toggle_mute", "mute", "unmute"])]
+        #[arg(allow_hyphen_values = true)]
+        #[arg(
+            help = "Sets the volume to the specified value, i.e. 50, 50%, -6dB, 0.5x or 32768raw"
+        )]
+        set: Option<VolumeSpec>,
+    },
+
+    #[command(visible_alias = "mv")]
+    #[command(about = "Moves the selected streams to the device given by --index or --name")]
+    Move {
+        #[arg(long)]
+        #[arg(help = "The index of the stream")]
+        #[arg(conflicts_with_all = ["app", "binary", "all"])]
+        stream: Option<u32>,
+
+        #[arg(short, long)]
+        #[arg(help = "The application name of the streams, i.e. Firefox")]
+        #[arg(conflicts_with_all = ["stream", "binary", "all"])]
+        app: Option<String>,
+
+        #[arg(long)]
+        #[arg(help = "The process binary of the streams, i.e. firefox")]
+        #[arg(conflicts_with_all = ["stream", "app", "all"])]
+        binary: Option<String>,
+
+        #[arg(long)]
+        #[arg(help = "Move every stream")]
+        #[arg(conflicts_with_all = ["stream", "app", "binary"])]
+        all: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum DefaultCommands {
+    #[command(about = "Sets the default sink, where new streams will play")]
+    Sink {
+        #[arg(help = "The index or name of the sink")]
+        device: String,
+
+        #[arg(short, long)]
+        #[arg(help = "Also move the streams that are already playing")]
+        move_streams: bool,
+    },
+
+    #[command(about = "Sets the default source, where new streams will record from")]
+    Source {
+        #[arg(help = "The index or name of the source")]
+        device: String,
+
+        #[arg(short, long)]
+        #[arg(help = "Also move the streams that are already recording")]
+        move_streams: bool,
+    },
+}
+
+/// Channel positions use Pulse's own names, i.e. front-left, rear-right or lfe
+fn parse_position(position: &str) -> Result<Position, String> {
+    match Position::from_string(position) {
+        Position::Invalid => Err(format!("{position} is not a channel position")),
+        position => Ok(position),
+    }
+}
+
+fn parse_balance(balance: &str) -> Result<f32, String> {
+    match balance.parse::<f32>() {
+        Ok(balance) if (-1.0..=1.0).contains(&balance) => Ok(balance),
+        _ => Err(format!("{balance} is not a number from -1.0 to 1.0")),
+    }
+}
+
+/// Devices can be given on the command line by either their index or their name,
+/// anything that parses as an index is treated as one.
+fn index_or_name(device: &str) -> (Option<u32>, Option<String>) {
+    match device.parse::<u32>() {
+        Ok(index) => (Some(index), None),
+        Err(_) => (None, Some(device.to_string())),
+    }
+}
+
+/// Timeouts are given in seconds, fractions included, i.e. --connect-timeout 0.5
+fn parse_seconds(seconds: &str) -> Result<Duration, String> {
+    match seconds.parse::<f64>() {
+        Ok(seconds) if seconds > 0.0 && seconds.is_finite() => Ok(Duration::from_secs_f64(seconds)),
+        _ => Err(format!("{seconds} is not a positive number of seconds")),
+    }
+}
+
+/// Everything but text is collected into a single report so it comes out as one document
+fn print_report(
+    report: &Report,
+    format: OutputFormat,
+    out: &mut dyn Write,
+) -> Result<(), RupamixError> {
+    let rendered = report.render(format)?;
+    writeln!(out, "{}", rendered.trim_end())?;
+    Ok(())
+}
+
+/// What setting a volume says when there was nothing to change
+fn print_unchanged(set: &VolumeSpec, out: &mut dyn Write) -> Result<(), RupamixError> {
+    writeln!(out, "\nThe current volume is already {set}")?;
+    Ok(())
+}
+
+/// Volume commands need to be told what to do, only --channel on its own prints something.
+/// This is checked before anything is run, so it never reaches the daemon either.
+fn check_action(command: &Commands) -> Result<(), RupamixError> {
+    let missing = match command {
+        Commands::Volume {
+            channel: None,
+            balance: None,
+            fade: None,
+            increase,
+            decrease,
+            toggle_mute,
+            mute,
+            unmute,
+            set,
+            ..
+        }
+        | Commands::Stream {
+            command:
+                StreamCommands::Volume {
+                    increase,
+                    decrease,
+                    toggle_mute,
+                    mute,
+                    unmute,
+                    set,
+                    ..
+                },
+        }
+        | Commands::Recording {
+            command:
+                StreamCommands::Volume {
+                    increase,
+                    decrease,
+                    toggle_mute,
+                    mute,
+                    unmute,
+                    set,
+                    ..
+                },
+        } => {
+            increase.is_zero()
+                && decrease.is_zero()
+                && !toggle_mute
+                && !mute
+                && !unmute
+                && set.is_none()
+        }
+        _ => false,
+    };
+
+    if missing {
+        Err(no_action())
+    } else {
+        Ok(())
+    }
+}
+
+fn no_action() -> RupamixError {
+    RupamixError::Usage(
+        "No action was specified, try --increase, --decrease, --set or --toggle-mute".to_string(),
+    )
+}
+
+/// Only commands that change something are sent to the daemon, anything that prints
+/// needs to print here.
+fn is_forwardable(command: &Commands) -> bool {
+    match command {
+        Commands::Volume {
+            channel: Some(_),
+            increase,
+            decrease,
+            set,
+            ..
+        } => !increase.is_zero() || !decrease.is_zero() || set.is_some(),
+        Commands::Volume { .. } | Commands::Default { .. } | Commands::Port { .. } => true,
+        Commands::Card { command } => !matches!(command, CardCommands::List),
+        Commands::Stream { command } | Commands::Recording { command } => {
+            !matches!(command, StreamCommands::List)
+        }
+        _ => false,
+    }
+}
+
+/// Errors are printed here, the kind of error decides the exit code. See RupamixError.
+fn main() -> ExitCode {
+    match Config::load().and_then(start) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn start(config: Config) -> Result<(), RupamixError> {
+    let matches = command(&config).get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    check_action(&cli.command)?;
+
+    if !cli.no_daemon && !own_server(&matches) && is_forwardable(&cli.command) {
+        let args: Vec<String> = std::env::args().collect();
+        daemon::forward(&args).unwrap_or_else(|| connect_and_run(cli, &config))
+    } else {
+        connect_and_run(cli, &config)
+    }
+}
+
+/// Whether the user picked a server for this command, with --server or PULSE_SERVER.
+/// The daemon is connected to the server from the config or the desktop's default,
+/// so such a command has to connect by itself instead of going through it.
+fn own_server(matches: &ArgMatches) -> bool {
+    matches!(
+        matches.value_source("server"),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+/// The Cli with the defaults the config file changes filled in
+fn command(config: &Config) -> Command {
+    let step = config.step_arg();
+    let with_step = |command: Command| {
+        command
+            .mut_arg("increase", |arg| arg.default_missing_value(step.clone()))
+            .mut_arg("decrease", |arg| arg.default_missing_value(step.clone()))
+    };
+
+    Cli::command()
+        .mut_arg("server", |arg| match &config.server {
+            Some(server) => arg.default_value(server.to_string()),
+            None => arg,
+        })
+        .mut_arg("max_volume", |arg| {
+            arg.default_value(config.max_volume().to_string())
+        })
+        .mut_arg("max_boost", |arg| {
+            arg.default_value(config.max_boost().to_string())
+        })
+        .mut_arg("format", |arg| {
+            arg.default_value(config.format().to_string())
+        })
+        .mut_subcommand("volume", with_step)
+        .mut_subcommand("stream", |command| {
+            command.mut_subcommand("volume", with_step)
+        })
+        .mut_subcommand("recording", |command| {
+            command.mut_subcommand("volume", with_step)
+        })
+}
+
+fn connect_and_run(cli: Cli, config: &Config) -> Result<(), RupamixError> {
+    let mut pulse = Pulse::connect_to(&ConnectOptions {
+        server: cli.server.clone(),
+        autospawn: !cli.no_autospawn,
+        timeout: cli.connect_timeout,
+        operation_timeout: cli.operation_timeout,
+        retries: cli.retries,
+    })?;
+    configure(&mut pulse, &cli, config);
+    run(cli, &mut pulse, config, &mut io::stdout())
+}
+
+/// Hands Pulse what it keeps from the config for as long as it is connected, the aliases and
+/// the limits of single devices
+fn configure(pulse: &mut Pulse, cli: &Cli, config: &Config) {
+    pulse.set_aliases(config.aliases.clone());
+
+    let global = volume_limits(cli, config);
+    for (device, limits) in &config.limits {
+        pulse.set_device_volume_limits(device, limits.over(global));
+    }
+}
+
+/// The limits every device without limits of its own gets
+fn volume_limits(cli: &Cli, config: &Config) -> VolumeLimits {
+    VolumeLimits::new(
+        cli.max_volume,
+        cli.max_boost,
+        cli.relative_to_base || config.relative_to_base.unwrap_or_default(),
+    )
+}
+
+/// Runs the command for the daemon, which parses it again on its side. What it prints goes
+/// to out, which is sent back to the client.
+fn run_forwarded(
+    pulse: &mut Pulse,
+    args: Vec<String>,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<(), RupamixError> {
+    let cli = command(config)
+        .try_get_matches_from(args)
+        .and_then(|matches| Cli::from_arg_matches(&matches))
+        .map_err(|e| RupamixError::Usage(e.to_string()))?;
+    if !is_forwardable(&cli.command) {
+        return Err(RupamixError::Usage(
+            "The daemon only runs commands that don't print".to_string(),
+        ));
+    }
+    run(cli, pulse, config, out)
+}
+
+fn run(
+    cli: Cli,
+    pulse: &mut Pulse,
+    config: &Config,
+    out: &mut dyn Write,
+) -> Result<(), RupamixError> {
+    pulse.set_volume_limits(volume_limits(&cli, config));
 
     match &cli.command {
         Commands::Print {
             sinks,
             sources,
             volume,
+            source_volume,
+            channels,
+            source_channels,
+            ..
+        } if cli.format != OutputFormat::Text => {
+            let report = Report {
+                default_sink: sinks.then(|| pulse.default_sink_name()),
+                default_source: sources.then(|| pulse.default_source_name()),
+                sinks: sinks.then(|| pulse.sink_records()),
+                sources: sources.then(|| pulse.source_records()),
+                sink: (*volume || *channels)
+                    .then(|| pulse.sink_record(cli.index, cli.name.clone()))
+                    .transpose()?,
+                source: (*source_volume || *source_channels)
+                    .then(|| pulse.source_record(cli.index, cli.name.clone()))
+                    .transpose()?,
+                ..Default::default()
+            };
+            print_report(&report, cli.format, out)?;
+        }
+        Commands::Print {
+            sinks,
+            sources,
+            volume,
+            source_volume,
+            channels,
+            source_channels,
+            unit,
         } => {
             if *sources {
-                pulse.print_sources()
+                pulse.print_sources()?;
             }
 
             if *sinks {
-                pulse.print_sinks()
+                pulse.print_sinks()?;
             }
 
             if *volume {
-                pulse.print_sink_volume(cli.index, cli.name);
+                pulse.print_sink_volume(cli.index, cli.name.clone(), *unit)?;
+            }
+
+            if *source_volume {
+                pulse.print_source_volume(cli.index, cli.name.clone(), *unit)?;
+            }
+
+            if *channels {
+                pulse.print_sink_channel_volumes(cli.index, cli.name.clone(), *unit)?;
+            }
+
+            if *source_channels {
+                pulse.print_source_channel_volumes(cli.index, cli.name, *unit)?;
+            }
+        }
+        Commands::Volume {
+            source,
+            channel: Some(position),
+            boost,
+            increase,
+            decrease,
+            set,
+            ..
+        } => {
+            let position = *position;
+            if *source {
+                if !increase.is_zero() {
+                    pulse.increase_source_channel_volume(
+                        position, *increase, cli.index, cli.name, *boost,
+                    )?;
+                } else if !decrease.is_zero() {
+                    pulse
+                        .decrease_source_channel_volume(position, *decrease, cli.index, cli.name)?;
+                } else if let Some(set) = set {
+                    pulse.set_source_channel_volume(position, *set, *boost, cli.index, cli.name)?;
+                } else {
+                    pulse.print_source_channel_volumes(
+                        cli.index,
+                        cli.name,
+                        VolumeUnit::default(),
+                    )?;
+                }
+            } else if !increase.is_zero() {
+                pulse.increase_sink_channel_volume(
+                    position, *increase, cli.index, cli.name, *boost,
+                )?;
+            } else if !decrease.is_zero() {
+                pulse.decrease_sink_channel_volume(position, *decrease, cli.index, cli.name)?;
+            } else if let Some(set) = set {
+                pulse.set_sink_channel_volume(position, *set, *boost, cli.index, cli.name)?;
+            } else {
+                pulse.print_sink_channel_volumes(cli.index, cli.name, VolumeUnit::default())?;
+            }
+        }
+        Commands::Volume {
+            source,
+            balance: Some(balance),
+            ..
+        } => {
+            if *source {
+                pulse.set_source_balance(*balance, cli.index, cli.name)?;
+            } else {
+                pulse.set_sink_balance(*balance, cli.index, cli.name)?;
+            }
+        }
+        Commands::Volume {
+            source,
+            fade: Some(fade),
+            ..
+        } => {
+            if *source {
+                pulse.set_source_fade(*fade, cli.index, cli.name)?;
+            } else {
+                pulse.set_sink_fade(*fade, cli.index, cli.name)?;
             }
         }
         Commands::Volume {
+            source: true,
             boost,
             increase,
             decrease,
             toggle_mute,
+            mute,
+            unmute,
             set,
+            ..
         } => {
-            if *increase > 0 {
-                pulse.increase_sink_volume(increase, cli.index, cli.name, *boost);
-            } else if *decrease > 0 {
-                pulse.decrease_sink_volume(decrease, cli.index, cli.name);
+            if !increase.is_zero() {
+                pulse.increase_source_volume(*increase, cli.index, cli.name, *boost)?;
+            } else if !decrease.is_zero() {
+                pulse.decrease_source_volume(*decrease, cli.index, cli.name)?;
             } else if *toggle_mute {
-                pulse.toggle_mute(cli.index, cli.name);
-            } else if set.is_some() {
-                pulse.set_sink_volume(set.unwrap(), *boost, cli.index, cli.name);
+                pulse.toggle_source_mute(cli.index, cli.name)?;
+            } else if *mute || *unmute {
+                pulse.set_source_mute(*mute, cli.index, cli.name)?;
+            } else if let Some(set) = set {
+                if !pulse.set_source_volume(*set, *boost, cli.index, cli.name)? {
+                    print_unchanged(set, out)?;
+                }
             } else {
-                println!("No action was specified")
+                return Err(no_action());
             }
         }
+        Commands::Volume {
+            source: false,
+            boost,
+            increase,
+            decrease,
+            toggle_mute,
+            mute,
+            unmute,
+            set,
+            ..
+        } => {
+            if !increase.is_zero() {
+                pulse.increase_sink_volume(*increase, cli.index, cli.name, *boost)?;
+            } else if !decrease.is_zero() {
+                pulse.decrease_sink_volume(*decrease, cli.index, cli.name)?;
+            } else if *toggle_mute {
+                pulse.toggle_mute(cli.index, cli.name)?;
+            } else if *mute || *unmute {
+                pulse.set_sink_mute(*mute, cli.index, cli.name)?;
+            } else if let Some(set) = set {
+                if !pulse.set_sink_volume(*set, *boost, cli.index, cli.name)? {
+                    print_unchanged(set, out)?;
+                }
+            } else {
+                return Err(no_action());
+            }
+        }
+        Commands::Status {
+            source,
+            bar,
+            template,
+        } => {
+            let status = if *source {
+                pulse.source_status(cli.index, cli.name)?
+            } else {
+                pulse.sink_status(cli.index, cli.name)?
+            };
+            writeln!(out, "{}", status.render(*bar, template))?;
+        }
+        Commands::Watch {
+            source,
+            bar,
+            template,
+        } => {
+            if matches!(cli.format, OutputFormat::Yaml | OutputFormat::Toml) {
+                return Err(RupamixError::Usage(
+                    "Watch only writes text or json".to_string(),
+                ));
+            }
+
+            if let Some(bar) = bar {
+                let status = |pulse: &Pulse| {
+                    if *source {
+                        pulse.source_status(cli.index, cli.name.clone())
+                    } else {
+                        pulse.sink_status(cli.index, cli.name.clone())
+                    }
+                };
+
+                // Print the status right away, then only when it actually changes
+                let mut last = status(pulse)?.render(*bar, template);
+                writeln!(out, "{last}")?;
+                out.flush()?;
+                pulse.watch(|pulse, _| {
+                    // The device can be gone for a while, i.e. between the default going away
+                    // and the server naming a new one, so just wait for the next change
+                    let Ok(status) = status(pulse) else {
+                        return Ok(());
+                    };
+                    let status = status.render(*bar, template);
+                    if status != last {
+                        writeln!(out, "{status}")?;
+                        out.flush()?;
+                        last = status;
+                    }
+                    Ok(())
+                })?;
+            } else {
+                pulse.watch(|_, event| {
+                    match cli.format {
+                        OutputFormat::Json => {
+                            writeln!(out, "{}", serde_json::to_string(event).unwrap_or_default())?
+                        }
+                        _ => writeln!(out, "{event}")?,
+                    }
+                    Ok(out.flush()?)
+                })?;
+            }
+        }
+        Commands::Info {
+            source,
+            device,
+            unit,
+        } => {
+            let (index, name) = match device {
+                Some(device) => index_or_name(device),
+                None => (cli.index, cli.name),
+            };
+            match (cli.format, *source) {
+                (OutputFormat::Text, true) => pulse.print_source_info(index, name, *unit)?,
+                (OutputFormat::Text, false) => pulse.print_sink_info(index, name, *unit)?,
+                (format, true) => {
+                    let report = Report {
+                        source: Some(pulse.source_info_record(index, name)?),
+                        ..Default::default()
+                    };
+                    print_report(&report, format, out)?;
+                }
+                (format, false) => {
+                    let report = Report {
+                        sink: Some(pulse.sink_info_record(index, name)?),
+                        ..Default::default()
+                    };
+                    print_report(&report, format, out)?;
+                }
+            }
+        }
+        Commands::Port { source, port } => {
+            if *source {
+                pulse.set_source_port(cli.index, cli.name, port)?;
+            } else {
+                pulse.set_sink_port(cli.index, cli.name, port)?;
+            }
+        }
+        Commands::Card { command } => match command {
+            CardCommands::List if cli.format != OutputFormat::Text => {
+                let report = Report {
+                    cards: Some(pulse.card_records()),
+                    ..Default::default()
+                };
+                print_report(&report, cli.format, out)?;
+            }
+            CardCommands::List => pulse.print_cards()?,
+            CardCommands::Profile { card, profile } => {
+                let (index, name) = index_or_name(card);
+                pulse.set_card_profile(index, name, profile)?;
+            }
+        },
+        Commands::Default { command } => match command {
+            DefaultCommands::Sink {
+                device,
+                move_streams,
+            } => {
+                let (index, name) = index_or_name(device);
+                pulse.set_default_sink(index, name, *move_streams)?;
+            }
+            DefaultCommands::Source {
+                device,
+                move_streams,
+            } => {
+                let (index, name) = index_or_name(device);
+                pulse.set_default_source(index, name, *move_streams)?;
+            }
+        },
+        Commands::Stream { command } => match command {
+            StreamCommands::List if cli.format != OutputFormat::Text => {
+                let report = Report {
+                    sink_inputs: Some(pulse.sink_input_records()),
+                    ..Default::default()
+                };
+                print_report(&report, cli.format, out)?;
+            }
+            StreamCommands::List => pulse.print_sink_inputs()?,
+            StreamCommands::Move {
+                stream,
+                app,
+                binary,
+                all,
+            } => {
+                if *all {
+                    pulse.move_all_sink_inputs(cli.index, cli.name)?;
+                } else {
+                    pulse.move_sink_inputs(
+                        *stream,
+                        app.clone(),
+                        binary.clone(),
+                        cli.index,
+                        cli.name,
+                    )?;
+                }
+            }
+            StreamCommands::Volume {
+                stream,
+                app,
+                binary,
+                boost,
+                increase,
+                decrease,
+                toggle_mute,
+                mute,
+                unmute,
+                set,
+            } => {
+                let (index, app, binary) = (*stream, app.clone(), binary.clone());
+                if !increase.is_zero() {
+                    pulse.increase_sink_input_volume(*increase, index, app, binary, *boost)?;
+                } else if !decrease.is_zero() {
+                    pulse.decrease_sink_input_volume(*decrease, index, app, binary)?;
+                } else if *toggle_mute {
+                    pulse.toggle_sink_input_mute(index, app, binary)?;
+                } else if *mute || *unmute {
+                    pulse.set_sink_input_mute(*mute, index, app, binary)?;
+                } else if let Some(set) = set {
+                    if !pulse.set_sink_input_volume(*set, *boost, index, app, binary)? {
+                        print_unchanged(set, out)?;
+                    }
+                } else {
+                    return Err(no_action());
+                }
+            }
+        },
+        Commands::Recording { command } => match command {
+            StreamCommands::List if cli.format != OutputFormat::Text => {
+                let report = Report {
+                    source_outputs: Some(pulse.source_output_records()),
+                    ..Default::default()
+                };
+                print_report(&report, cli.format, out)?;
+            }
+            StreamCommands::List => pulse.print_source_outputs()?,
+            StreamCommands::Move {
+                stream,
+                app,
+                binary,
+                all,
+            } => {
+                if *all {
+                    pulse.move_all_source_outputs(cli.index, cli.name)?;
+                } else {
+                    pulse.move_source_outputs(
+                        *stream,
+                        app.clone(),
+                        binary.clone(),
+                        cli.index,
+                        cli.name,
+                    )?;
+                }
+            }
+            StreamCommands::Volume {
+                stream,
+                app,
+                binary,
+                boost,
+                increase,
+                decrease,
+                toggle_mute,
+                mute,
+                unmute,
+                set,
+            } => {
+                let (index, app, binary) = (*stream, app.clone(), binary.clone());
+                if !increase.is_zero() {
+                    pulse.increase_source_output_volume(*increase, index, app, binary, *boost)?;
+                } else if !decrease.is_zero() {
+                    pulse.decrease_source_output_volume(*decrease, index, app, binary)?;
+                } else if *toggle_mute {
+                    pulse.toggle_source_output_mute(index, app, binary)?;
+                } else if *mute || *unmute {
+                    pulse.set_source_output_mute(*mute, index, app, binary)?;
+                } else if let Some(set) = set {
+                    if !pulse.set_source_output_volume(*set, *boost, index, app, binary)? {
+                        print_unchanged(set, out)?;
+                    }
+                } else {
+                    return Err(no_action());
+                }
+            }
+        },
+        Commands::Daemon => {
+            daemon::serve(pulse, |pulse, args, out| {
+                run_forwarded(pulse, args, config, out)
+            })?;
+        }
         #[cfg(feature = "extractor")]
         Commands::Extractor { one_percent } => {
             if *one_percent {
@@ -148,3 +1148,173 @@ fn main() -> Result<(), &'static str> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pulse::volume::Volume;
+    use rupamix::pulse_controller::backend::AudioBackend;
+    use rupamix::pulse_controller::fake_backend::FakeBackend;
+    use rupamix::pulse_wrappers::device::Device;
+
+    /// Runs a command line against a fake desktop, see FakeBackend::desktop
+    fn run_fake(args: &[&str]) -> (Result<(), RupamixError>, FakeBackend) {
+        run_fake_with(&Config::default(), args)
+    }
+
+    /// run_fake, with Pulse set up from config the way connect_and_run does it
+    fn run_fake_with(config: &Config, args: &[&str]) -> (Result<(), RupamixError>, FakeBackend) {
+        let backend = FakeBackend::desktop();
+        let mut pulse = Pulse::with_backend(Box::new(backend.clone())).unwrap();
+
+        let matches = command(config).get_matches_from(["rupamix"].iter().chain(args));
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        configure(&mut pulse, &cli, config);
+
+        (run(cli, &mut pulse, config, &mut io::sink()), backend)
+    }
+
+    #[test]
+    fn test_volume_command() {
+        let (result, mut backend) = run_fake(&["--name", "hdmi", "volume", "--increase"]);
+
+        assert!(result.is_ok());
+        let hdmi = backend.sink(1).unwrap().unwrap();
+        assert_eq!(55, hdmi.get_volume_as_pct());
+        assert_eq!(50, backend.sink(0).unwrap().unwrap().get_volume_as_pct());
+    }
+
+    #[test]
+    fn test_device_limits_from_the_config() {
+        let config = Config::parse(
+            r#"
+            [aliases.sinks]
+            tv = "hdmi"
+
+            [limits.tv]
+            max_volume = 60
+            "#,
+        )
+        .unwrap();
+
+        let (result, mut backend) =
+            run_fake_with(&config, &["--name", "tv", "volume", "--set", "80"]);
+        assert!(result.is_ok());
+        assert_eq!(60, backend.sink(1).unwrap().unwrap().get_volume_as_pct());
+
+        let (result, mut backend) = run_fake_with(&config, &["volume", "--set", "80"]);
+        assert!(result.is_ok());
+        assert_eq!(80, backend.sink(0).unwrap().unwrap().get_volume_as_pct());
+    }
+
+    #[test]
+    fn test_watch_follows_the_selected_sink() {
+        let config = Config::default();
+        let mut backend = FakeBackend::desktop();
+        let mut pulse = Pulse::with_backend(Box::new(backend.clone())).unwrap();
+        let matches = command(&config).get_matches_from([
+            "rupamix",
+            "--name",
+            "hdmi",
+            "watch",
+            "--bar",
+            "plain",
+            "-t",
+            "{name} {volume}",
+        ]);
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+        let mut out = Vec::new();
+
+        // Changed behind our back, the events arrive once the watch runs
+        pulse.subscribe().unwrap();
+        backend.set_sink_mute(0, true).unwrap();
+        let mut volume = *backend.sink(1).unwrap().unwrap().volume().borrow();
+        volume.set(2, Volume(Volume::NORMAL.0 / 4));
+        backend.set_sink_volume(1, &volume).unwrap();
+
+        // The fake server ends the watch once it has nothing left to send
+        let result = run(cli, &mut pulse, &config, &mut out);
+
+        assert!(matches!(result, Err(RupamixError::Connection(_))));
+        assert_eq!("hdmi 50\nhdmi 25\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_stream_commands() {
+        let (result, mut backend) = run_fake(&["stream", "volume", "--app", "firefox", "-m"]);
+
+        assert!(result.is_ok());
+        assert!(backend.sink_input(0).unwrap().unwrap().mute());
+
+        let (result, _) = run_fake(&["stream", "volume", "--app", "spotify", "-m"]);
+        assert_eq!(5, result.unwrap_err().exit_code());
+    }
+
+    #[test]
+    fn test_default_command() {
+        let (result, mut backend) = run_fake(&["default", "sink", "HDMI", "--move-streams"]);
+
+        assert!(result.is_ok());
+        assert_eq!("hdmi", backend.default_sink_name());
+        assert_eq!(1, backend.sink_input(0).unwrap().unwrap().sink());
+    }
+
+    #[test]
+    fn test_forwarded_commands() {
+        let config = Config::default();
+        let mut pulse = Pulse::with_backend(Box::new(FakeBackend::desktop())).unwrap();
+        let mut forward = |args: &[&str]| {
+            let args = ["rupamix"].iter().chain(args).map(|arg| arg.to_string());
+            let mut out = Vec::new();
+            let res = run_forwarded(&mut pulse, args.collect(), &config, &mut out);
+            (res, String::from_utf8(out).unwrap())
+        };
+
+        let (res, out) = forward(&["volume", "--set", "50"]);
+        assert!(res.is_ok());
+        assert_eq!("\nThe current volume is already 50%\n", out);
+
+        for args in [
+            &["louder"][..],
+            &["volume"],
+            &["stream", "volume"],
+            &["print"],
+        ] {
+            let (res, out) = forward(args);
+            assert_eq!(2, res.unwrap_err().exit_code(), "{args:?}");
+            assert!(out.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_own_server() {
+        let config = Config {
+            server: Some("tcp:audio-box".parse().unwrap()),
+            ..Default::default()
+        };
+        // A variable no other test touches, PULSE_SERVER itself is read by the parallel tests
+        let env = "RUPAMIX_TEST_PULSE_SERVER";
+        let command = || command(&config).mut_arg("server", |arg| arg.env(env));
+        let own =
+            |args: &[&str]| own_server(&command().get_matches_from(["rupamix"].iter().chain(args)));
+
+        assert!(!own(&["volume", "--increase"]));
+        assert!(own(&["--server", "tcp:other-box", "volume", "--increase"]));
+
+        std::env::set_var(env, "unix:/run/container/pulse/native");
+        let from_env = own(&["volume", "--increase"]);
+        std::env::remove_var(env);
+        assert!(from_env);
+    }
+
+    #[test]
+    fn test_volume_needs_an_action() {
+        let (result, _) = run_fake(&["volume", "--source"]);
+
+        assert!(matches!(result, Err(RupamixError::Usage(_))));
+        assert!(check_action(&Cli::parse_from(["rupamix", "volume", "-i"]).command).is_ok());
+        assert!(
+            check_action(&Cli::parse_from(["rupamix", "recording", "volume"]).command).is_err()
+        );
+    }
+}
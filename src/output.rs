@@ -0,0 +1,315 @@
+//! Structured versions of what the print commands show, for scripts that would otherwise
+//! have to scrape the text tables. Everything is collected into a Report which is then
+//! rendered once, so that asking for several things still gives a single valid document.
+use crate::pulse_wrappers::card_info::{PulseCardInfo, PulseCardProfile};
+use crate::pulse_wrappers::device::Device;
+use crate::pulse_wrappers::device_metadata::DeviceMetadata;
+use crate::pulse_wrappers::port_info::PulsePortInfo;
+use crate::pulse_wrappers::sink_input_info::PulseSinkInputInfo;
+use crate::pulse_wrappers::source_output_info::PulseSourceOutputInfo;
+use crate::pulse_wrappers::volume_spec::{VolumeSpec, VolumeUnit};
+use pulse::channelmap::Position;
+use pulse::volume::Volume;
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_ascii_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "yaml" | "yml" => Ok(OutputFormat::Yaml),
+            "toml" => Ok(OutputFormat::Toml),
+            _ => Err(format!(
+                "{format} is not a format, try text, json, yaml or toml"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let format = match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+            OutputFormat::Yaml => "yaml",
+            OutputFormat::Toml => "toml",
+        };
+        write!(f, "{format}")
+    }
+}
+
+pub enum OutputError {
+    /// Text is printed as we go, there is no document to render
+    NotStructured,
+    Serialize(String),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputError::NotStructured => write!(f, "text output can not be rendered as a report"),
+            OutputError::Serialize(e) => write!(f, "unable to serialize the report: {e}"),
+        }
+    }
+}
+
+/// A volume in every unit we know, so scripts can pick whichever suits them
+#[derive(Debug, Clone, Serialize)]
+pub struct VolumeRecord {
+    pub percent: f64,
+    pub db: f64,
+    pub linear: f64,
+    pub raw: u32,
+}
+
+impl From<Volume> for VolumeRecord {
+    fn from(volume: Volume) -> Self {
+        let unit = |unit| match VolumeSpec::from_volume(volume, unit) {
+            VolumeSpec::Percent(value) | VolumeSpec::Decibel(value) | VolumeSpec::Linear(value) => {
+                value
+            }
+            VolumeSpec::Raw(raw) => f64::from(raw),
+        };
+
+        VolumeRecord {
+            percent: unit(VolumeUnit::Percent),
+            db: unit(VolumeUnit::Decibel),
+            linear: unit(VolumeUnit::Linear),
+            raw: volume.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelRecord {
+    pub position: String,
+    pub volume: VolumeRecord,
+}
+
+/// A sink or a source
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceRecord {
+    pub index: u32,
+    pub name: String,
+    pub description: String,
+    pub default: bool,
+    pub mute: bool,
+    pub volume: VolumeRecord,
+    pub base_volume: VolumeRecord,
+    pub channels: Vec<ChannelRecord>,
+    pub active_port: Option<String>,
+    pub ports: Vec<PulsePortInfo>,
+    /// Only filled in by the info command
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<DeviceMetadata>,
+}
+
+impl DeviceRecord {
+    pub fn from_device<T>(device: &impl Device<T>, default: bool) -> DeviceRecord {
+        DeviceRecord {
+            index: device.index(),
+            name: device.name().to_string(),
+            description: device.description().to_string(),
+            default,
+            mute: device.mute(),
+            volume: loudest_channel(device),
+            base_volume: VolumeRecord::from(*device.base_volume().borrow()),
+            channels: channel_records(device),
+            active_port: device.active_port().map(String::from),
+            ports: device.ports().to_vec(),
+            metadata: None,
+        }
+    }
+
+    /// from_device plus the metadata, for the info command
+    pub fn detailed<T>(device: &impl Device<T>, default: bool) -> DeviceRecord {
+        DeviceRecord {
+            metadata: device.metadata().cloned(),
+            ..DeviceRecord::from_device(device, default)
+        }
+    }
+}
+
+/// A sink input or a source output, device is the index of the sink or source it is using
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamRecord {
+    pub index: u32,
+    pub name: String,
+    pub application_name: String,
+    pub process_binary: String,
+    pub device: u32,
+    pub mute: bool,
+    pub volume: VolumeRecord,
+    pub channels: Vec<ChannelRecord>,
+}
+
+impl From<&PulseSinkInputInfo> for StreamRecord {
+    fn from(input: &PulseSinkInputInfo) -> Self {
+        StreamRecord {
+            index: input.index(),
+            name: input.name().to_string(),
+            application_name: input.application_name().to_string(),
+            process_binary: input.process_binary().to_string(),
+            device: input.sink(),
+            mute: input.mute(),
+            volume: loudest_channel(input),
+            channels: channel_records(input),
+        }
+    }
+}
+
+impl From<&PulseSourceOutputInfo> for StreamRecord {
+    fn from(output: &PulseSourceOutputInfo) -> Self {
+        StreamRecord {
+            index: output.index(),
+            name: output.name().to_string(),
+            application_name: output.application_name().to_string(),
+            process_binary: output.process_binary().to_string(),
+            device: output.source(),
+            mute: output.mute(),
+            volume: loudest_channel(output),
+            channels: channel_records(output),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CardRecord {
+    pub index: u32,
+    pub name: String,
+    pub description: String,
+    pub driver: String,
+    pub active_profile: Option<String>,
+    pub profiles: Vec<PulseCardProfile>,
+}
+
+impl From<&PulseCardInfo> for CardRecord {
+    fn from(card: &PulseCardInfo) -> Self {
+        CardRecord {
+            index: card.index(),
+            name: card.name().to_string(),
+            description: card.description().to_string(),
+            driver: card.driver().to_string(),
+            active_profile: card.active_profile().map(String::from),
+            profiles: card.profiles().to_vec(),
+        }
+    }
+}
+
+/// Everything a single print asked for. Only the parts that were asked for get serialized.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_sink: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sinks: Option<Vec<DeviceRecord>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<DeviceRecord>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sink: Option<DeviceRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<DeviceRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sink_inputs: Option<Vec<StreamRecord>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_outputs: Option<Vec<StreamRecord>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cards: Option<Vec<CardRecord>>,
+}
+
+impl Report {
+    pub fn render(&self, format: OutputFormat) -> Result<String, OutputError> {
+        let rendered = match format {
+            OutputFormat::Text => return Err(OutputError::NotStructured),
+            OutputFormat::Json => serde_json::to_string_pretty(self).map_err(|e| e.to_string()),
+            OutputFormat::Yaml => serde_yaml::to_string(self).map_err(|e| e.to_string()),
+            OutputFormat::Toml => toml::to_string(self).map_err(|e| e.to_string()),
+        };
+
+        rendered.map_err(OutputError::Serialize)
+    }
+}
+
+fn loudest_channel<T>(device: &impl Device<T>) -> VolumeRecord {
+    let volume = device.volume();
+    let loudest = volume.borrow().get().iter().copied().max();
+    VolumeRecord::from(loudest.unwrap_or(Volume::MUTED))
+}
+
+fn channel_records<T>(device: &impl Device<T>) -> Vec<ChannelRecord> {
+    let volume = device.volume();
+    let volume = volume.borrow();
+    device
+        .channel_map()
+        .get()
+        .iter()
+        .zip(volume.get())
+        .map(|(position, vol)| ChannelRecord {
+            position: Position::to_string(*position)
+                .unwrap_or_default()
+                .into_owned(),
+            volume: VolumeRecord::from(*vol),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> Report {
+        Report {
+            default_sink: Some("Test".to_string()),
+            sink_inputs: Some(vec![StreamRecord {
+                index: 10,
+                name: "Playback".to_string(),
+                application_name: "Test App".to_string(),
+                process_binary: "test-app".to_string(),
+                device: 1,
+                mute: false,
+                volume: VolumeRecord::from(Volume::NORMAL),
+                channels: vec![],
+            }]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert!(matches!("JSON".parse(), Ok(OutputFormat::Json)));
+        assert!(matches!("yml".parse(), Ok(OutputFormat::Yaml)));
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_json_skips_what_was_not_asked_for() {
+        let json = report().render(OutputFormat::Json).ok().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["default_sink"], "Test");
+        assert_eq!(value["sink_inputs"][0]["volume"]["raw"], 65536);
+        assert!(value.get("sinks").is_none());
+    }
+
+    #[test]
+    fn test_toml_and_yaml_render() {
+        assert!(report().render(OutputFormat::Toml).is_ok());
+        assert!(report().render(OutputFormat::Yaml).is_ok());
+        assert!(report().render(OutputFormat::Text).is_err());
+    }
+}
@@ -1,286 +1,1454 @@
+pub mod backend;
+pub mod connection;
 pub mod device_manager;
+pub mod device_pattern;
+pub mod events;
+#[cfg(any(test, feature = "fake-backend"))]
+pub mod fake_backend;
+pub mod handle;
 pub mod pulse_driver;
+#[cfg(feature = "async")]
+pub mod reply;
+#[cfg(test)]
+mod test_server;
+
+use crate::config::Aliases;
+use crate::error::RupamixError;
+use crate::output::{CardRecord, DeviceRecord, StreamRecord};
+use crate::pulse_controller::backend::AudioBackend;
+use crate::pulse_controller::connection::ConnectOptions;
+use crate::pulse_controller::device_manager::DeviceManager;
+use crate::pulse_controller::events::{ChangeEvent, ChangeFacility, ChangeKind};
+use crate::pulse_controller::pulse_driver::PulseDriver;
+use crate::status::Status;
+
+use crate::pulse_wrappers::device::{Device, VolumeLimits};
+use crate::pulse_wrappers::server_info::PulseServerInfo;
+use crate::pulse_wrappers::sink_info::PulseSinkInfo;
+use crate::pulse_wrappers::sink_input_info::PulseSinkInputInfo;
+use crate::pulse_wrappers::source_info::PulseSourceInfo;
+use crate::pulse_wrappers::source_output_info::PulseSourceOutputInfo;
+use crate::pulse_wrappers::volume_spec::{VolumeSpec, VolumeUnit};
+
+use pulse::channelmap::Position;
+use pulse::volume::ChannelVolumes;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::os::unix::io::RawFd;
+use std::rc::Rc;
+
+pub struct Pulse {
+    backend: Box<dyn AudioBackend>,
+    server_info: Rc<RefCell<PulseServerInfo>>,
+    device_manager: Rc<RefCell<DeviceManager>>,
+    events: Rc<RefCell<VecDeque<ChangeEvent>>>,
+}
+
+impl Pulse {
+    /// This gets a fully ready to use Pulse struct. To achieve that we
+    /// connect with empty server_info and device fields.
+    /// To fill those empty fields we call sync to get the current global state of PulseAudio
+    pub fn new() -> Result<Pulse, RupamixError> {
+        Pulse::connect_to(&ConnectOptions::default())
+    }
+
+    /// The same as new, but for a given server, flags and timeout. See ConnectOptions.
+    pub fn connect_to(options: &ConnectOptions) -> Result<Pulse, RupamixError> {
+        Pulse::with_backend(Box::new(PulseDriver::connect_to_pulse(options)?))
+    }
+
+    /// The same as new, but on top of any backend, i.e. a FakeBackend in tests
+    pub fn with_backend(backend: Box<dyn AudioBackend>) -> Result<Pulse, RupamixError> {
+        let mut pulse = Pulse::connect(backend);
+
+        pulse.sync()?;
+        Ok(pulse)
+    }
+
+    fn connect(backend: Box<dyn AudioBackend>) -> Pulse {
+        Pulse {
+            backend,
+            server_info: Rc::new(RefCell::new(PulseServerInfo::default())),
+            device_manager: Rc::new(RefCell::new(DeviceManager::default())),
+            events: Rc::new(RefCell::new(VecDeque::new())),
+        }
+    }
+
+    /// Return the device manager to a calling controller
+    pub fn device_manager(&self) -> Rc<RefCell<DeviceManager>> {
+        self.device_manager.clone()
+    }
+
+    /// Return the server info to a calling controller
+    pub fn server_info(&self) -> Rc<RefCell<PulseServerInfo>> {
+        self.server_info.clone()
+    }
+
+    /// Get the current state of Pulse Audio
+    /// This includes info about the server, the sinks, the sources, the streams and the cards
+    pub fn sync(&mut self) -> Result<(), RupamixError> {
+        self.get_server_info()?;
+        self.get_source_info()?;
+        self.get_sink_info()?;
+        self.get_sink_input_info()?;
+        self.get_source_output_info()?;
+        self.get_card_info()
+    }
+
+    /// Our access to Pulse state is a oneshot, if the state changes, or if we tried to change it,
+    /// we need to ask Pulse for the world state again.
+    /// Sync is not idempotent so we need to reset the fields set by sync
+    /// before we call sync on it again.
+    pub fn update(&mut self) -> Result<(), RupamixError> {
+        self.device_manager.borrow_mut().reset();
+        self.sync()
+    }
+
+    /// Subscribes to changes to sinks, sources, streams, cards and the server. The events are
+    /// queued up as the mainloop runs, and applied by process_events.
+    pub fn subscribe(&mut self) -> Result<(), RupamixError> {
+        self.backend.subscribe(self.events.clone())
+    }
+
+    /// Runs the mainloop once so queued events can arrive, blocking until there is something
+    /// to do if block is set
+    pub fn iterate(&mut self, block: bool) -> Result<(), RupamixError> {
+        self.backend.iterate(block)
+    }
+
+    /// Lets a blocking iterate also return once fd can be read, so the caller can wait for
+    /// the server and for something of its own, i.e. a socket, at the same time
+    pub fn watch_fd(&mut self, fd: RawFd) -> Result<(), RupamixError> {
+        self.backend.watch_fd(fd)
+    }
+
+    /// Stops watching fd, which has to happen before it is closed
+    pub fn unwatch_fd(&mut self, fd: RawFd) {
+        self.backend.unwatch_fd(fd)
+    }
+
+    /// Keeps our state up to date one object at a time rather than with update. on_change is
+    /// called after each queued change has been applied.
+    pub fn process_events<F>(&mut self, mut on_change: F) -> Result<(), RupamixError>
+    where
+        F: FnMut(&Pulse, &ChangeEvent),
+    {
+        // Applying a change runs the mainloop, which can queue more events,
+        // so the queue must not stay borrowed while we do it
+        loop {
+            let next = self.events.borrow_mut().pop_front();
+            let Some(event) = next else {
+                break;
+            };
+            self.apply_change(&event)?;
+            on_change(self, &event);
+        }
+
+        Ok(())
+    }
+
+    /// Subscribes and then processes events as they come in. This only returns if the
+    /// connection goes away or on_change fails, i.e. because nobody reads what it writes.
+    pub fn watch<F>(&mut self, mut on_change: F) -> Result<(), RupamixError>
+    where
+        F: FnMut(&Pulse, &ChangeEvent) -> Result<(), RupamixError>,
+    {
+        self.subscribe()?;
+
+        loop {
+            self.iterate(true)?;
+
+            let mut res = Ok(());
+            self.process_events(|pulse, event| {
+                if res.is_ok() {
+                    res = on_change(pulse, event);
+                }
+            })?;
+            res?;
+        }
+    }
+
+    /// Brings a single object up to date after a subscribe event
+    fn apply_change(&mut self, event: &ChangeEvent) -> Result<(), RupamixError> {
+        let manager = self.device_manager.clone();
+        let index = event.index;
+
+        match (event.facility, event.kind) {
+            (ChangeFacility::Sink, ChangeKind::Removed) => manager.borrow_mut().remove_sink(index),
+            (ChangeFacility::Source, ChangeKind::Removed) => {
+                manager.borrow_mut().remove_source(index)
+            }
+            (ChangeFacility::SinkInput, ChangeKind::Removed) => {
+                manager.borrow_mut().remove_sink_input(index)
+            }
+            (ChangeFacility::SourceOutput, ChangeKind::Removed) => {
+                manager.borrow_mut().remove_source_output(index)
+            }
+            (ChangeFacility::Card, ChangeKind::Removed) => manager.borrow_mut().remove_card(index),
+            (ChangeFacility::Sink, _) => {
+                if let Some(sink) = self.backend.sink(index)? {
+                    manager.borrow_mut().update_sink(sink);
+                }
+            }
+            (ChangeFacility::Source, _) => {
+                if let Some(source) = self.backend.source(index)? {
+                    manager.borrow_mut().update_source(source);
+                }
+            }
+            (ChangeFacility::SinkInput, _) => {
+                if let Some(input) = self.backend.sink_input(index)? {
+                    manager.borrow_mut().update_sink_input(input);
+                }
+            }
+            (ChangeFacility::SourceOutput, _) => {
+                if let Some(output) = self.backend.source_output(index)? {
+                    manager.borrow_mut().update_source_output(output);
+                }
+            }
+            (ChangeFacility::Server, _) => {
+                self.get_server_info()?;
+                let server_info = self.server_info.borrow();
+                // The new default may not have reached us yet, its own event will follow
+                let _ = manager
+                    .borrow_mut()
+                    .set_default_sink(&server_info.default_sink_name);
+                let _ = manager
+                    .borrow_mut()
+                    .set_default_source(&server_info.default_source_name);
+            }
+            (ChangeFacility::Card, _) => {
+                if let Some(card) = self.backend.card(index)? {
+                    manager.borrow_mut().update_card(card);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// This calls the device managers print soruces
+    pub fn print_sources(&self) -> Result<(), RupamixError> {
+        self.device_manager.borrow_mut().print_sources()?;
+
+        Ok(())
+    }
+
+    /// This calls the device managers print sinks
+    pub fn print_sinks(&self) -> Result<(), RupamixError> {
+        self.device_manager.borrow_mut().print_sinks()?;
+
+        Ok(())
+    }
+
+    /// This calls the device managers print sink inputs
+    pub fn print_sink_inputs(&self) -> Result<(), RupamixError> {
+        self.device_manager.borrow_mut().print_sink_inputs()?;
+
+        Ok(())
+    }
+
+    /// This calls the device managers print source outputs
+    pub fn print_source_outputs(&self) -> Result<(), RupamixError> {
+        self.device_manager.borrow_mut().print_source_outputs()?;
+
+        Ok(())
+    }
+
+    /// This calls the device managers print cards
+    pub fn print_cards(&self) -> Result<(), RupamixError> {
+        self.device_manager.borrow_mut().print_cards()?;
+
+        Ok(())
+    }
+
+    /// Sets how loud devices and streams may get, see VolumeLimits
+    pub fn set_volume_limits(&mut self, limits: VolumeLimits) {
+        self.device_manager.borrow_mut().set_volume_limits(limits);
+    }
+
+    /// Short names from the config that can be used wherever a device name can
+    pub fn set_aliases(&mut self, aliases: Aliases) {
+        self.device_manager.borrow_mut().set_aliases(aliases);
+    }
+
+    /// Sets how loud a single sink or source may get, overriding set_volume_limits
+    pub fn set_device_volume_limits(&mut self, name: &str, limits: VolumeLimits) {
+        self.device_manager
+            .borrow_mut()
+            .set_device_volume_limits(name, limits);
+    }
+
+    /// The name of the sink new streams play on
+    pub fn default_sink_name(&self) -> String {
+        self.server_info.borrow().default_sink_name.clone()
+    }
+
+    /// The name of the source new streams record from
+    pub fn default_source_name(&self) -> String {
+        self.server_info.borrow().default_source_name.clone()
+    }
+
+    /// Structured version of print_sinks
+    pub fn sink_records(&self) -> Vec<DeviceRecord> {
+        let default = self.default_sink_name();
+        self.device_manager
+            .borrow_mut()
+            .sinks()
+            .iter()
+            .map(|sink| {
+                let sink = sink.borrow();
+                DeviceRecord::from_device(&*sink, sink.name() == default)
+            })
+            .collect()
+    }
+
+    /// Structured version of print_sources
+    pub fn source_records(&self) -> Vec<DeviceRecord> {
+        let default = self.default_source_name();
+        self.device_manager
+            .borrow_mut()
+            .sources()
+            .iter()
+            .map(|source| {
+                let source = source.borrow();
+                DeviceRecord::from_device(&*source, source.name() == default)
+            })
+            .collect()
+    }
+
+    /// Structured version of print_sink_volume and print_sink_channel_volumes
+    pub fn sink_record(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<DeviceRecord, RupamixError> {
+        let sink = self.select_sink(index, name)?;
+        let sink = sink.borrow();
+        Ok(DeviceRecord::from_device(
+            &*sink,
+            sink.name() == self.default_sink_name(),
+        ))
+    }
+
+    /// Structured version of print_sink_info
+    pub fn sink_info_record(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<DeviceRecord, RupamixError> {
+        let sink = self.select_sink(index, name)?;
+        let sink = sink.borrow();
+        Ok(DeviceRecord::detailed(
+            &*sink,
+            sink.name() == self.default_sink_name(),
+        ))
+    }
+
+    /// Structured version of print_source_info
+    pub fn source_info_record(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<DeviceRecord, RupamixError> {
+        let source = self.select_source(index, name)?;
+        let source = source.borrow();
+        Ok(DeviceRecord::detailed(
+            &*source,
+            source.name() == self.default_source_name(),
+        ))
+    }
+
+    /// Prints everything we know about the selected sink
+    pub fn print_sink_info(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+        unit: VolumeUnit,
+    ) -> Result<(), RupamixError> {
+        self.select_sink(index, name)?.borrow().print_info(unit);
+
+        Ok(())
+    }
+
+    /// Prints everything we know about the selected source
+    pub fn print_source_info(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+        unit: VolumeUnit,
+    ) -> Result<(), RupamixError> {
+        self.select_source(index, name)?.borrow().print_info(unit);
+
+        Ok(())
+    }
+
+    /// Structured version of print_source_volume and print_source_channel_volumes
+    pub fn source_record(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<DeviceRecord, RupamixError> {
+        let source = self.select_source(index, name)?;
+        let source = source.borrow();
+        Ok(DeviceRecord::from_device(
+            &*source,
+            source.name() == self.default_source_name(),
+        ))
+    }
+
+    /// Structured version of print_sink_inputs, no streams is just an empty list here
+    pub fn sink_input_records(&self) -> Vec<StreamRecord> {
+        self.device_manager
+            .borrow_mut()
+            .sink_inputs()
+            .iter()
+            .map(|input| StreamRecord::from(&*input.borrow()))
+            .collect()
+    }
+
+    /// Structured version of print_source_outputs
+    pub fn source_output_records(&self) -> Vec<StreamRecord> {
+        self.device_manager
+            .borrow_mut()
+            .source_outputs()
+            .iter()
+            .map(|output| StreamRecord::from(&*output.borrow()))
+            .collect()
+    }
+
+    /// Every card with its profiles, for structured output
+    pub fn card_records(&self) -> Vec<CardRecord> {
+        self.device_manager
+            .borrow_mut()
+            .cards()
+            .iter()
+            .map(|card| CardRecord::from(&*card.borrow()))
+            .collect()
+    }
+
+    /// A status bar summary of the selected sink
+    pub fn sink_status(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<Status, RupamixError> {
+        Ok(Status::from_device(
+            &*self.select_sink(index, name)?.borrow(),
+        ))
+    }
+
+    /// A status bar summary of the selected source
+    pub fn source_status(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<Status, RupamixError> {
+        Ok(Status::from_device(
+            &*self.select_source(index, name)?.borrow(),
+        ))
+    }
+
+    /// Here we want to prink the volume of a specific sink.
+    /// Sinks can be specified with either an index or a name.
+    /// If neither are supplied, we will print the info from the default
+    pub fn print_sink_volume(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+        unit: VolumeUnit,
+    ) -> Result<(), RupamixError> {
+        self.device_manager
+            .borrow_mut()
+            .print_sink_volume(index, name, unit)?;
+
+        Ok(())
+    }
+
+    /// Same as print_sink_volume, but for a source
+    pub fn print_source_volume(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+        unit: VolumeUnit,
+    ) -> Result<(), RupamixError> {
+        self.device_manager
+            .borrow_mut()
+            .print_source_volume(index, name, unit)?;
+
+        Ok(())
+    }
+
+    /// Prints the volume of every channel of the selected sink
+    pub fn print_sink_channel_volumes(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+        unit: VolumeUnit,
+    ) -> Result<(), RupamixError> {
+        self.select_sink(index, name)?
+            .borrow()
+            .print_channel_volumes(unit);
+
+        Ok(())
+    }
+
+    /// Prints the volume of every channel of the selected source
+    pub fn print_source_channel_volumes(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+        unit: VolumeUnit,
+    ) -> Result<(), RupamixError> {
+        self.select_source(index, name)?
+            .borrow()
+            .print_channel_volumes(unit);
+
+        Ok(())
+    }
+
+    /// This method asks the running Pulse server for it's sever info and
+    /// stores that data in our thin wrapper around pulse audio's state
+    fn get_server_info(&mut self) -> Result<(), RupamixError> {
+        *self.server_info.borrow_mut() = self.backend.server_info()?;
+
+        Ok(())
+    }
+
+    /// Get a list of all pulse audio's sources and store those in our device manager
+    fn get_source_info(&mut self) -> Result<(), RupamixError> {
+        for source in self.backend.sources()? {
+            self.device_manager.borrow_mut().add_source(source);
+        }
+
+        self.device_manager
+            .borrow_mut()
+            .set_default_source(&self.server_info.borrow().default_source_name)?;
+
+        Ok(())
+    }
+
+    /// Get a list of all pulse audio's sinks and store those in our device manager
+    fn get_sink_info(&mut self) -> Result<(), RupamixError> {
+        for sink in self.backend.sinks()? {
+            self.device_manager.borrow_mut().add_sink(sink);
+        }
+
+        self.device_manager
+            .borrow_mut()
+            .set_default_sink(&self.server_info.borrow().default_sink_name)?;
+
+        Ok(())
+    }
+
+    /// Get a list of all the streams currently playing and store those in our device manager
+    fn get_sink_input_info(&mut self) -> Result<(), RupamixError> {
+        for input in self.backend.sink_inputs()? {
+            self.device_manager.borrow_mut().add_sink_input(input);
+        }
+
+        Ok(())
+    }
+
+    /// Get a list of all the streams currently recording and store those in our device manager
+    fn get_source_output_info(&mut self) -> Result<(), RupamixError> {
+        for output in self.backend.source_outputs()? {
+            self.device_manager.borrow_mut().add_source_output(output);
+        }
+
+        Ok(())
+    }
+
+    /// Get all cards with their profiles
+    fn get_card_info(&mut self) -> Result<(), RupamixError> {
+        for card in self.backend.cards()? {
+            self.device_manager.borrow_mut().add_card(card);
+        }
+
+        Ok(())
+    }
+
+    /// Updates the volume of a particular sink by that sink's index
+    /// This method is what actually reaches out to the running server to request
+    /// the change in volume
+    fn update_sink_volume(
+        &mut self,
+        index: u32,
+        volume: ChannelVolumes,
+    ) -> Result<(), RupamixError> {
+        self.backend.set_sink_volume(index, &volume)
+    }
+
+    /// Updates the volume of a particular source by that source's index
+    /// This method is what actually reaches out to the running server to request
+    /// the change in volume
+    fn update_source_volume(
+        &mut self,
+        index: u32,
+        volume: ChannelVolumes,
+    ) -> Result<(), RupamixError> {
+        self.backend.set_source_volume(index, &volume)
+    }
+
+    /// Updates the mute flag of a particular sink by that sink's index
+    fn update_sink_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        self.backend.set_sink_mute(index, mute)
+    }
+
+    /// Updates the mute flag of a particular source by that source's index
+    fn update_source_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        self.backend.set_source_mute(index, mute)
+    }
+
+    /// Updates the volume of a particular sink input by that stream's index
+    fn update_sink_input_volume(
+        &mut self,
+        index: u32,
+        volume: ChannelVolumes,
+    ) -> Result<(), RupamixError> {
+        self.backend.set_sink_input_volume(index, &volume)
+    }
+
+    /// Updates the mute flag of a particular sink input by that stream's index
+    fn update_sink_input_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        self.backend.set_sink_input_mute(index, mute)
+    }
+
+    /// Updates the volume of a particular source output by that stream's index
+    fn update_source_output_volume(
+        &mut self,
+        index: u32,
+        volume: ChannelVolumes,
+    ) -> Result<(), RupamixError> {
+        self.backend.set_source_output_volume(index, &volume)
+    }
+
+    /// Updates the mute flag of a particular source output by that stream's index
+    fn update_source_output_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        self.backend.set_source_output_mute(index, mute)
+    }
+
+    /// Get the sink by index or name, the default sink if neither are supplied
+    fn select_sink(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<Rc<RefCell<PulseSinkInfo>>, RupamixError> {
+        Ok(self.device_manager.borrow_mut().get_sink(index, name)?)
+    }
+
+    /// Get the source by index or name, the default source if neither are supplied
+    fn select_source(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<Rc<RefCell<PulseSourceInfo>>, RupamixError> {
+        Ok(self.device_manager.borrow_mut().get_source(index, name)?)
+    }
+
+    /// Get the source outputs selected by index, application name or process binary
+    fn select_source_outputs(
+        &self,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<Vec<Rc<RefCell<PulseSourceOutputInfo>>>, RupamixError> {
+        Ok(self
+            .device_manager
+            .borrow_mut()
+            .get_source_outputs(index, app, binary)?)
+    }
+
+    /// Get the sink inputs selected by index, application name or process binary
+    fn select_sink_inputs(
+        &self,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<Vec<Rc<RefCell<PulseSinkInputInfo>>>, RupamixError> {
+        Ok(self
+            .device_manager
+            .borrow_mut()
+            .get_sink_inputs(index, app, binary)?)
+    }
+
+    /// Sets the volume of the sink selected by index or name (default if neither are supplied).
+    /// Returns false, without asking the server for anything, if the volume already was vol.
+    pub fn set_sink_volume(
+        &mut self,
+        vol: VolumeSpec,
+        boost: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<bool, RupamixError> {
+        let sink = self.select_sink(index, name)?;
+
+        if !sink.borrow_mut().set_volume(vol, boost) {
+            return Ok(false);
+        }
+
+        self.update_sink_volume(sink.borrow().index(), *sink.borrow().volume().borrow())?;
+
+        Ok(true)
+    }
+
+    /// This method first get the sink by index or name (default if neither are supplied)
+    /// It then asks the sink to increase it's volume. This is just a state change in our
+    /// representation of the sink, so finally it uses that new rep to call our method that
+    /// will interface with the PA server to make the change for real
+    pub fn increase_sink_volume(
+        &mut self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+        boost: bool,
+    ) -> Result<(), RupamixError> {
+        let sink = self.select_sink(index, name)?;
+
+        sink.borrow_mut().increase_volume(inc, boost);
+
+        self.update_sink_volume(sink.borrow().index(), *sink.borrow().volume().borrow())?;
+
+        Ok(())
+    }
+
+    /// This method first get the sink by index or name (default if neither are supplied)
+    /// It then asks the sink to decrease it's volume. This is just a state change in our
+    /// representation of the sink, so finally it uses that new rep to call our method that
+    /// will interface with the PA server to make the change for real
+    pub fn decrease_sink_volume(
+        &mut self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let sink = self.select_sink(index, name)?;
+
+        sink.borrow_mut().decrease_volume(inc);
+
+        self.update_sink_volume(sink.borrow().index(), *sink.borrow().volume().borrow())?;
+
+        Ok(())
+    }
+
+    /// This method first get the sink by index or name (default if neither are supplied)
+    /// It then asks the sink to toggle_mute. This is just a state change in our
+    /// representation of the sink, so finally it uses that new rep to call our method that
+    /// will interface with the PA server to make the change for real
+    pub fn toggle_mute(
+        &mut self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let sink = self.select_sink(index, name)?;
+
+        sink.borrow_mut().toggle_mute();
+
+        self.update_sink_mute(sink.borrow().index(), sink.borrow().mute())?;
+
+        Ok(())
+    }
+
+    /// Mutes or unmutes the sink selected by index or name (default if neither are supplied).
+    /// The volume is left untouched, so unmuting brings back the same level.
+    pub fn set_sink_mute(
+        &mut self,
+        mute: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let sink = self.select_sink(index, name)?;
+
+        sink.borrow_mut().set_mute(mute);
+
+        self.update_sink_mute(sink.borrow().index(), mute)?;
+
+        Ok(())
+    }
+
+    /// The source counterpart of set_sink_volume
+    pub fn set_source_volume(
+        &mut self,
+        vol: VolumeSpec,
+        boost: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<bool, RupamixError> {
+        let source = self.select_source(index, name)?;
+
+        if !source.borrow_mut().set_volume(vol, boost) {
+            return Ok(false);
+        }
+
+        self.update_source_volume(source.borrow().index(), *source.borrow().volume().borrow())?;
+
+        Ok(true)
+    }
+
+    /// The source counterpart of increase_sink_volume, i.e. for your microphone
+    pub fn increase_source_volume(
+        &mut self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+        boost: bool,
+    ) -> Result<(), RupamixError> {
+        let source = self.select_source(index, name)?;
+
+        source.borrow_mut().increase_volume(inc, boost);
+
+        self.update_source_volume(source.borrow().index(), *source.borrow().volume().borrow())?;
+
+        Ok(())
+    }
+
+    /// The source counterpart of decrease_sink_volume, i.e. for your microphone
+    pub fn decrease_source_volume(
+        &mut self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let source = self.select_source(index, name)?;
+
+        source.borrow_mut().decrease_volume(inc);
+
+        self.update_source_volume(source.borrow().index(), *source.borrow().volume().borrow())?;
+
+        Ok(())
+    }
+
+    /// The source counterpart of toggle_mute, i.e. for a push to talk binding
+    pub fn toggle_source_mute(
+        &mut self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let source = self.select_source(index, name)?;
+
+        source.borrow_mut().toggle_mute();
+
+        self.update_source_mute(source.borrow().index(), source.borrow().mute())?;
+
+        Ok(())
+    }
+
+    /// The source counterpart of set_sink_mute
+    pub fn set_source_mute(
+        &mut self,
+        mute: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let source = self.select_source(index, name)?;
+
+        source.borrow_mut().set_mute(mute);
+
+        self.update_source_mute(source.borrow().index(), mute)?;
+
+        Ok(())
+    }
+
+    /// Sets the volume of the channels at position, i.e. front-left, on the selected sink
+    pub fn set_sink_channel_volume(
+        &mut self,
+        position: Position,
+        vol: VolumeSpec,
+        boost: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let sink = self.select_sink(index, name)?;
+
+        sink.borrow_mut().set_channel_volume(position, vol, boost)?;
+
+        self.update_sink_volume(sink.borrow().index(), *sink.borrow().volume().borrow())?;
+
+        Ok(())
+    }
+
+    /// Increases the volume of the channels at position on the selected sink
+    pub fn increase_sink_channel_volume(
+        &mut self,
+        position: Position,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+        boost: bool,
+    ) -> Result<(), RupamixError> {
+        let sink = self.select_sink(index, name)?;
+
+        sink.borrow_mut()
+            .increase_channel_volume(position, inc, boost)?;
+
+        self.update_sink_volume(sink.borrow().index(), *sink.borrow().volume().borrow())?;
+
+        Ok(())
+    }
+
+    /// Decreases the volume of the channels at position on the selected sink
+    pub fn decrease_sink_channel_volume(
+        &mut self,
+        position: Position,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let sink = self.select_sink(index, name)?;
 
-use crate::pulse_controller::device_manager::{DeviceError, DeviceManager};
-use crate::pulse_controller::pulse_driver::PulseDriver;
+        sink.borrow_mut().decrease_channel_volume(position, inc)?;
 
-use crate::pulse_wrappers::device::Device;
-use crate::pulse_wrappers::server_info::PulseServerInfo;
-use crate::pulse_wrappers::sink_info::PulseSinkInfo;
+        self.update_sink_volume(sink.borrow().index(), *sink.borrow().volume().borrow())?;
 
-use pulse::callbacks::ListResult;
-use pulse::volume::ChannelVolumes;
+        Ok(())
+    }
 
-use std::cell::RefCell;
-use std::rc::Rc;
+    /// Sets the left/right balance of the selected sink, from -1.0 (left) to 1.0 (right)
+    pub fn set_sink_balance(
+        &mut self,
+        balance: f32,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let sink = self.select_sink(index, name)?;
 
-pub struct Pulse {
-    driver: PulseDriver,
-    server_info: Rc<RefCell<PulseServerInfo>>,
-    device_manager: Rc<RefCell<DeviceManager>>,
-}
+        sink.borrow_mut().set_balance(balance)?;
 
-impl Default for Pulse {
-    fn default() -> Self {
-        let driver = PulseDriver::connect_to_pulse().unwrap();
+        self.update_sink_volume(sink.borrow().index(), *sink.borrow().volume().borrow())?;
 
-        Pulse {
-            driver,
-            server_info: Rc::new(RefCell::new(PulseServerInfo::default())),
-            device_manager: Rc::new(RefCell::new(DeviceManager::default())),
-        }
+        Ok(())
     }
-}
 
-impl Pulse {
-    /// This gets a fully ready to use Pulse struct. To achieve that we
-    /// created a default with empty server_info and device fields.
-    /// To fill those empty fields we call sync to get the current global state of PulseAudio
-    pub fn new() -> Pulse {
-        let mut pulse = Pulse::default();
+    /// Sets the rear/front fade of the selected sink, from -1.0 (rear) to 1.0 (front)
+    pub fn set_sink_fade(
+        &mut self,
+        fade: f32,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let sink = self.select_sink(index, name)?;
 
-        pulse.sync();
-        pulse
+        sink.borrow_mut().set_fade(fade)?;
+
+        self.update_sink_volume(sink.borrow().index(), *sink.borrow().volume().borrow())?;
+
+        Ok(())
     }
 
-    /// Return the device manager to a calling controller
-    pub fn device_manager(&self) -> Rc<RefCell<DeviceManager>> {
-        self.device_manager.clone()
+    /// Sets the volume of the channels at position, i.e. front-left, on the selected source
+    pub fn set_source_channel_volume(
+        &mut self,
+        position: Position,
+        vol: VolumeSpec,
+        boost: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let source = self.select_source(index, name)?;
+
+        source
+            .borrow_mut()
+            .set_channel_volume(position, vol, boost)?;
+
+        self.update_source_volume(source.borrow().index(), *source.borrow().volume().borrow())?;
+
+        Ok(())
     }
 
-    /// Return the server info to a calling controller
-    pub fn server_info(&self) -> Rc<RefCell<PulseServerInfo>> {
-        self.server_info.clone()
+    /// Increases the volume of the channels at position on the selected source
+    pub fn increase_source_channel_volume(
+        &mut self,
+        position: Position,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+        boost: bool,
+    ) -> Result<(), RupamixError> {
+        let source = self.select_source(index, name)?;
+
+        source
+            .borrow_mut()
+            .increase_channel_volume(position, inc, boost)?;
+
+        self.update_source_volume(source.borrow().index(), *source.borrow().volume().borrow())?;
+
+        Ok(())
     }
 
-    /// Get the current state of Pulse Audio
-    /// This includes info about the server, the sinks, and the sources
-    pub fn sync(&mut self) {
-        self.get_server_info();
-        // Currently we throw away these Results
-        if let Err(res) = self.get_source_info() {
-            res.print_err_and_panic()
-        } else if let Err(res) = self.get_sink_info() {
-            res.print_err_and_panic()
-        }
+    /// Decreases the volume of the channels at position on the selected source
+    pub fn decrease_source_channel_volume(
+        &mut self,
+        position: Position,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let source = self.select_source(index, name)?;
+
+        source.borrow_mut().decrease_channel_volume(position, inc)?;
+
+        self.update_source_volume(source.borrow().index(), *source.borrow().volume().borrow())?;
+
+        Ok(())
     }
 
-    /// Our access to Pulse state is a oneshot, if the state changes, or if we tried to change it,
-    /// we need to ask Pulse for the world state again.
-    /// Sync is not idempotent so we need to reset the fields set by sync
-    /// before we call sync on it again.
-    pub fn update(&mut self) {
-        self.device_manager.borrow_mut().reset();
-        self.sync();
+    /// Sets the left/right balance of the selected source, from -1.0 (left) to 1.0 (right)
+    pub fn set_source_balance(
+        &mut self,
+        balance: f32,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let source = self.select_source(index, name)?;
+
+        source.borrow_mut().set_balance(balance)?;
+
+        self.update_source_volume(source.borrow().index(), *source.borrow().volume().borrow())?;
+
+        Ok(())
     }
 
-    /// This calls the device managers print soruces
-    pub fn print_sources(&self) {
-        if let Err(e) = self.device_manager.borrow_mut().print_sources() {
-            e.print_err_and_panic();
+    /// Sets the rear/front fade of the selected source, from -1.0 (rear) to 1.0 (front)
+    pub fn set_source_fade(
+        &mut self,
+        fade: f32,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let source = self.select_source(index, name)?;
+
+        source.borrow_mut().set_fade(fade)?;
+
+        self.update_source_volume(source.borrow().index(), *source.borrow().volume().borrow())?;
+
+        Ok(())
+    }
+
+    /// Sets the volume of every stream selected by index, application name or binary.
+    /// Returns false if every one of them already was at vol.
+    pub fn set_sink_input_volume(
+        &mut self,
+        vol: VolumeSpec,
+        boost: bool,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<bool, RupamixError> {
+        let mut changed = false;
+
+        for input in self.select_sink_inputs(index, app, binary)? {
+            if !input.borrow_mut().set_volume(vol, boost) {
+                continue;
+            }
+
+            self.update_sink_input_volume(
+                input.borrow().index(),
+                *input.borrow().volume().borrow(),
+            )?;
+            changed = true;
         }
+
+        Ok(changed)
     }
 
-    /// This calls the device managers print sinks
-    pub fn print_sinks(&self) {
-        if let Err(e) = self.device_manager.borrow_mut().print_sinks() {
-            e.print_err_and_panic();
+    /// Increases the volume of every stream selected by index, application name or binary
+    pub fn increase_sink_input_volume(
+        &mut self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+        boost: bool,
+    ) -> Result<(), RupamixError> {
+        for input in self.select_sink_inputs(index, app, binary)? {
+            input.borrow_mut().increase_volume(inc, boost);
+
+            self.update_sink_input_volume(
+                input.borrow().index(),
+                *input.borrow().volume().borrow(),
+            )?;
         }
+
+        Ok(())
     }
 
-    /// Here we want to prink the volume of a specific sink.
-    /// Sinks can be specified with either an index or a name.
-    /// If neither are supplied, we will print the info from the default
-    pub fn print_sink_volume(&self, index: Option<u32>, name: Option<String>) {
-        if let Err(e) = self
-            .device_manager
-            .borrow_mut()
-            .print_sink_volume(index, name)
-        {
-            e.print_err_and_panic()
+    /// Decreases the volume of every stream selected by index, application name or binary
+    pub fn decrease_sink_input_volume(
+        &mut self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<(), RupamixError> {
+        for input in self.select_sink_inputs(index, app, binary)? {
+            input.borrow_mut().decrease_volume(inc);
+
+            self.update_sink_input_volume(
+                input.borrow().index(),
+                *input.borrow().volume().borrow(),
+            )?;
         }
+
+        Ok(())
     }
 
-    /// This method asks the running Pulse server for it's sever info and
-    /// stores that data in our thin wrapper around pulse audio's state
-    fn get_server_info(&mut self) {
-        let server_info = self.server_info.clone();
+    /// Mutes or unmutes every stream selected by index, application name or binary.
+    /// Streams have their own mute flag on the server, so the volume is left alone.
+    pub fn set_sink_input_mute(
+        &mut self,
+        mute: bool,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<(), RupamixError> {
+        for input in self.select_sink_inputs(index, app, binary)? {
+            input.borrow_mut().set_mute(mute);
 
-        let op = self
-            .driver
-            .introspector
-            .borrow()
-            .get_server_info(move |info| server_info.borrow_mut().update(info));
+            self.update_sink_input_mute(input.borrow().index(), mute)?;
+        }
 
-        self.driver
-            .wait_for_op(op)
-            .expect("Wait for op exited prematurely");
+        Ok(())
     }
 
-    /// Get a list of all pulse audio's sources and store those in our device manager
-    fn get_source_info(&mut self) -> Result<(), DeviceError> {
-        let manager = self.device_manager.clone();
+    /// Flips the mute flag of every stream selected by index, application name or binary
+    pub fn toggle_sink_input_mute(
+        &mut self,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<(), RupamixError> {
+        for input in self.select_sink_inputs(index, app, binary)? {
+            input.borrow_mut().toggle_mute();
 
-        let op =
-            self.driver
-                .introspector
-                .borrow()
-                .get_source_info_list(move |result| match result {
-                    ListResult::Item(info) => {
-                        manager.borrow_mut().add_source(info);
-                    }
-                    ListResult::Error => {}
-                    ListResult::End => {}
-                });
-
-        self.driver
-            .wait_for_op(op)
-            .expect("Wait for op exited prematurely");
+            self.update_sink_input_mute(input.borrow().index(), input.borrow().mute())?;
+        }
 
-        self.device_manager
-            .borrow_mut()
-            .set_default_source(&self.server_info.borrow().default_source_name)
+        Ok(())
     }
 
-    /// Get a list of all pulse audio's sinks and store those in our device manager
-    fn get_sink_info(&mut self) -> Result<(), DeviceError> {
-        let manager = self.device_manager.clone();
+    /// Sets the capture volume of every recording stream selected by index, application name
+    /// or binary
+    pub fn set_source_output_volume(
+        &mut self,
+        vol: VolumeSpec,
+        boost: bool,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<bool, RupamixError> {
+        let mut changed = false;
+
+        for output in self.select_source_outputs(index, app, binary)? {
+            if !output.borrow_mut().set_volume(vol, boost) {
+                continue;
+            }
+
+            self.update_source_output_volume(
+                output.borrow().index(),
+                *output.borrow().volume().borrow(),
+            )?;
+            changed = true;
+        }
 
-        let op = self
-            .driver
-            .introspector
-            .borrow()
-            .get_sink_info_list(move |result| match result {
-                ListResult::Item(info) => {
-                    manager.borrow_mut().add_sink(info);
-                }
-                ListResult::Error => {}
-                ListResult::End => {}
-            });
+        Ok(changed)
+    }
 
-        self.driver
-            .wait_for_op(op)
-            .expect("Wait for op exited prematurely");
+    /// Increases the capture volume of every recording stream selected by index, application
+    /// name or binary
+    pub fn increase_source_output_volume(
+        &mut self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+        boost: bool,
+    ) -> Result<(), RupamixError> {
+        for output in self.select_source_outputs(index, app, binary)? {
+            output.borrow_mut().increase_volume(inc, boost);
+
+            self.update_source_output_volume(
+                output.borrow().index(),
+                *output.borrow().volume().borrow(),
+            )?;
+        }
 
-        self.device_manager
-            .borrow_mut()
-            .set_default_sink(&self.server_info.borrow().default_sink_name)
+        Ok(())
     }
 
-    /// Updates the volume of a particular sink by that sink's index
-    /// This method is what actually reaches out to the running server to request
-    /// the change in volume
-    fn update_sink_volume(&mut self, index: u32, volume: ChannelVolumes) {
-        let op = self
-            .driver
-            .introspector
-            .borrow_mut()
-            .set_sink_volume_by_index(index, &volume, Some(Box::new(move |_success| ())));
+    /// Decreases the capture volume of every recording stream selected by index, application
+    /// name or binary
+    pub fn decrease_source_output_volume(
+        &mut self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<(), RupamixError> {
+        for output in self.select_source_outputs(index, app, binary)? {
+            output.borrow_mut().decrease_volume(inc);
+
+            self.update_source_output_volume(
+                output.borrow().index(),
+                *output.borrow().volume().borrow(),
+            )?;
+        }
 
-        self.driver
-            .wait_for_op(op)
-            .expect("Wait for op exited prematurely");
+        Ok(())
     }
 
-    pub fn set_sink_volume(
+    /// Mutes or unmutes every recording stream selected by index, application name or binary
+    pub fn set_source_output_mute(
         &mut self,
-        vol: u8,
-        boost: bool,
+        mute: bool,
         index: Option<u32>,
-        name: Option<String>,
-    ) {
-        let mut sink: Option<Rc<RefCell<PulseSinkInfo>>> = None;
-        let res = self.device_manager.borrow_mut().get_sink(index, name);
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<(), RupamixError> {
+        for output in self.select_source_outputs(index, app, binary)? {
+            output.borrow_mut().set_mute(mute);
 
-        match res {
-            Ok(inner) => sink = Some(inner),
-            Err(e) => e.print_err_and_panic(),
+            self.update_source_output_mute(output.borrow().index(), mute)?;
         }
 
-        let sink = sink.unwrap();
+        Ok(())
+    }
+
+    /// Flips the mute flag of every recording stream selected by index, application name
+    /// or binary
+    pub fn toggle_source_output_mute(
+        &mut self,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<(), RupamixError> {
+        for output in self.select_source_outputs(index, app, binary)? {
+            output.borrow_mut().toggle_mute();
 
-        sink.borrow_mut().set_volume(vol, boost);
+            self.update_source_output_mute(output.borrow().index(), output.borrow().mute())?;
+        }
 
-        self.update_sink_volume(sink.borrow().index(), sink.borrow().volume().take());
+        Ok(())
     }
 
-    /// This method first get the sink by index or name (default if neither are supplied)
-    /// It then asks the sink to increase it's volume. This is just a state change in our
-    /// representation of the sink, so finally it uses that new rep to call our method that
-    /// will interface with the PA server to make the change for real
-    pub fn increase_sink_volume(
+    /// Makes the sink selected by index or name the server's default sink. New streams will
+    /// play there; if move_streams is set the streams that are already playing follow it too.
+    pub fn set_default_sink(
         &mut self,
-        inc: &u8,
         index: Option<u32>,
         name: Option<String>,
-        boost: bool,
-    ) {
-        let mut sink: Option<Rc<RefCell<PulseSinkInfo>>> = None;
-        let res = self.device_manager.borrow_mut().get_sink(index, name);
+        move_streams: bool,
+    ) -> Result<(), RupamixError> {
+        let sink = self.select_sink(index, name)?;
+        let (sink_index, sink_name) = (sink.borrow().index(), sink.borrow().name().to_string());
+
+        self.backend.set_default_sink(&sink_name)?;
+
+        // Keep our own view of the server in step with the change we just made
+        self.device_manager
+            .borrow_mut()
+            .set_default_sink(&sink_name)?;
+        self.server_info.borrow_mut().default_sink_name = sink_name;
 
-        match res {
-            Ok(inner) => sink = Some(inner),
-            Err(e) => e.print_err_and_panic(),
+        if move_streams {
+            self.move_all_sink_inputs(Some(sink_index), None)?;
         }
 
-        let sink = sink.unwrap();
+        Ok(())
+    }
 
-        sink.borrow_mut().increase_volume(inc, boost);
+    /// Makes the source selected by index or name the server's default source. New recording
+    /// streams will use it; if move_streams is set the current ones are moved too.
+    pub fn set_default_source(
+        &mut self,
+        index: Option<u32>,
+        name: Option<String>,
+        move_streams: bool,
+    ) -> Result<(), RupamixError> {
+        let source = self.select_source(index, name)?;
+        let (source_index, source_name) =
+            (source.borrow().index(), source.borrow().name().to_string());
+
+        self.backend.set_default_source(&source_name)?;
+
+        self.device_manager
+            .borrow_mut()
+            .set_default_source(&source_name)?;
+        self.server_info.borrow_mut().default_source_name = source_name;
+
+        if move_streams {
+            self.move_all_source_outputs(Some(source_index), None)?;
+        }
 
-        self.update_sink_volume(sink.borrow().index(), sink.borrow().volume().take());
+        Ok(())
     }
 
-    /// This method first get the sink by index or name (default if neither are supplied)
-    /// It then asks the sink to decrease it's volume. This is just a state change in our
-    /// representation of the sink, so finally it uses that new rep to call our method that
-    /// will interface with the PA server to make the change for real
-    pub fn decrease_sink_volume(&mut self, inc: &u8, index: Option<u32>, name: Option<String>) {
-        let mut sink: Option<Rc<RefCell<PulseSinkInfo>>> = None;
-        let res = self.device_manager.borrow_mut().get_sink(index, name);
+    /// Switches the sink selected by index or name to another port, i.e. from the speakers to
+    /// the headphone jack. The port can be given the same ways as a device name.
+    pub fn set_sink_port(
+        &mut self,
+        index: Option<u32>,
+        name: Option<String>,
+        port: &str,
+    ) -> Result<(), RupamixError> {
+        let sink = self.select_sink(index, name)?;
+        let sink_index = sink.borrow().index();
+        let port = sink.borrow().find_port(port)?.name.clone();
+
+        self.backend.set_sink_port(sink_index, &port)?;
+        self.update()
+    }
+
+    /// The source counterpart of set_sink_port
+    pub fn set_source_port(
+        &mut self,
+        index: Option<u32>,
+        name: Option<String>,
+        port: &str,
+    ) -> Result<(), RupamixError> {
+        let source = self.select_source(index, name)?;
+        let source_index = source.borrow().index();
+        let port = source.borrow().find_port(port)?.name.clone();
+
+        self.backend.set_source_port(source_index, &port)?;
+        self.update()
+    }
+
+    /// Switches the card selected by index or name to a profile, i.e. from A2DP to HSP/HFP.
+    /// The profile can be given the same ways as a device name. Pulse replaces the card's
+    /// sinks and sources when its profile changes, so we sync again afterwards.
+    pub fn set_card_profile(
+        &mut self,
+        index: Option<u32>,
+        name: Option<String>,
+        profile: &str,
+    ) -> Result<(), RupamixError> {
+        let card = self.device_manager.borrow_mut().get_card(index, name)?;
+        let card_index = card.borrow().index();
+        let profile = card.borrow().find_profile(profile)?.name.clone();
+
+        self.backend.set_card_profile(card_index, &profile)?;
+        self.update()
+    }
+
+    /// Moves a single sink input to the sink with the given index. This method is what
+    /// actually reaches out to the running server to request the move
+    pub fn move_sink_input(&mut self, index: u32, sink_index: u32) -> Result<(), RupamixError> {
+        self.backend.move_sink_input(index, sink_index)
+    }
+
+    /// Moves a single source output to the source with the given index
+    pub fn move_source_output(
+        &mut self,
+        index: u32,
+        source_index: u32,
+    ) -> Result<(), RupamixError> {
+        self.backend.move_source_output(index, source_index)
+    }
 
-        match res {
-            Ok(inner) => sink = Some(inner),
-            Err(e) => e.print_err_and_panic(),
+    /// Moves the streams selected by stream index, application name or binary to the sink
+    /// selected by sink_index or sink_name, the default sink if neither are supplied.
+    pub fn move_sink_inputs(
+        &mut self,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+        sink_index: Option<u32>,
+        sink_name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let inputs = self.select_sink_inputs(index, app, binary)?;
+        let sink = self.select_sink(sink_index, sink_name)?.borrow().index();
+
+        for input in inputs {
+            if input.borrow().sink() != sink {
+                let index = input.borrow().index();
+                self.move_sink_input(index, sink)?;
+            }
         }
 
-        let sink = sink.unwrap();
+        Ok(())
+    }
 
-        sink.borrow_mut().decrease_volume(inc);
+    /// Moves every stream that is currently playing to the sink selected by sink_index or
+    /// sink_name, the default sink if neither are supplied.
+    pub fn move_all_sink_inputs(
+        &mut self,
+        sink_index: Option<u32>,
+        sink_name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let inputs = self.device_manager.borrow_mut().sink_inputs().to_vec();
+        let sink = self.select_sink(sink_index, sink_name)?.borrow().index();
+
+        for input in inputs {
+            if input.borrow().sink() != sink {
+                let index = input.borrow().index();
+                self.move_sink_input(index, sink)?;
+            }
+        }
 
-        self.update_sink_volume(sink.borrow().index(), sink.borrow().volume().take());
+        Ok(())
     }
 
-    /// This method first get the sink by index or name (default if neither are supplied)
-    /// It then asks the sink to toggle_mute. This is just a state change in our
-    /// representation of the sink, so finally it uses that new rep to call our method that
-    /// will interface with the PA server to make the change for real
-    pub fn toggle_mute(&mut self, index: Option<u32>, name: Option<String>) {
-        let mut sink: Option<Rc<RefCell<PulseSinkInfo>>> = None;
-        let res = self.device_manager.borrow_mut().get_sink(index, name);
+    /// The recording counterpart of move_sink_inputs, the streams are moved to a source
+    pub fn move_source_outputs(
+        &mut self,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+        source_index: Option<u32>,
+        source_name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let outputs = self.select_source_outputs(index, app, binary)?;
+        let source = self
+            .select_source(source_index, source_name)?
+            .borrow()
+            .index();
 
-        match res {
-            Ok(inner) => sink = Some(inner),
-            Err(e) => e.print_err_and_panic(),
+        for output in outputs {
+            if output.borrow().source() != source {
+                let index = output.borrow().index();
+                self.move_source_output(index, source)?;
+            }
         }
 
-        let sink = sink.unwrap();
+        Ok(())
+    }
 
-        sink.borrow_mut()
-            .toggle_mute()
-            .expect("Unable to toggle mute");
+    /// The recording counterpart of move_all_sink_inputs, the streams are moved to a source
+    pub fn move_all_source_outputs(
+        &mut self,
+        source_index: Option<u32>,
+        source_name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        let outputs = self.device_manager.borrow_mut().source_outputs().to_vec();
+        let source = self
+            .select_source(source_index, source_name)?
+            .borrow()
+            .index();
+
+        for output in outputs {
+            if output.borrow().source() != source {
+                let index = output.borrow().index();
+                self.move_source_output(index, source)?;
+            }
+        }
 
-        self.update_sink_volume(sink.borrow().index(), sink.borrow().volume().take());
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::pulse_controller::fake_backend::{fake_sink_input, FakeBackend};
+    use crate::pulse_controller::test_server::{TestServer, TEST_SINK, TEST_SINK2};
     static BOOST: bool = false;
-    static INC: u8 = 5;
+    static INC: VolumeSpec = VolumeSpec::Percent(5.0);
 
-    fn setup() -> Pulse {
-        Pulse::new()
+    /// Keep the server in a named binding, binding it to _ would stop it straight away
+    fn setup() -> (Pulse, TestServer) {
+        let server = TestServer::start();
+        let pulse = Pulse::connect_to(&server.connect_options()).unwrap();
+        (pulse, server)
     }
 
     fn get_default(pulse: &Pulse) -> Rc<RefCell<PulseSinkInfo>> {
@@ -292,27 +1460,158 @@ mod tests {
             .unwrap()
     }
 
-    // everything below here must be run on a single thread
-    // run with the following flags
-    // --ignored --test-threads=1
+    fn fake_pulse() -> (Pulse, FakeBackend) {
+        let backend = FakeBackend::desktop();
+        let pulse = Pulse::with_backend(Box::new(backend.clone())).unwrap();
+        (pulse, backend)
+    }
+
+    fn fake_sink_pct(backend: &mut FakeBackend, index: u32) -> u8 {
+        backend.sink(index).unwrap().unwrap().get_volume_as_pct()
+    }
+
+    #[test]
+    fn test_sync_reads_the_backend() {
+        let (pulse, _) = fake_pulse();
+        let manager = pulse.device_manager();
+
+        assert_eq!(2, manager.borrow().sinks_count());
+        assert_eq!(1, manager.borrow().sources_count());
+        assert_eq!(1, manager.borrow().cards_count());
+        assert_eq!("speakers", get_default(&pulse).borrow().name());
+    }
+
+    #[test]
+    fn test_volume_changes_reach_the_backend() {
+        let (mut pulse, mut backend) = fake_pulse();
+
+        pulse.increase_sink_volume(INC, None, None, BOOST).unwrap();
+        assert_eq!(55, fake_sink_pct(&mut backend, 0));
+
+        pulse
+            .set_sink_volume(VolumeSpec::Percent(20.0), BOOST, None, Some("hdmi".into()))
+            .unwrap();
+        assert_eq!(20, fake_sink_pct(&mut backend, 1));
+        assert_eq!(55, fake_sink_pct(&mut backend, 0));
+
+        pulse.update().unwrap();
+        assert_eq!(55, get_default(&pulse).borrow().get_volume_as_pct());
+    }
+
+    #[test]
+    fn test_writes_that_change_nothing_keep_the_volume() {
+        let (mut pulse, mut backend) = fake_pulse();
+        let full = VolumeSpec::Percent(100.0);
+
+        assert!(pulse.set_sink_volume(full, BOOST, None, None).unwrap());
+        assert!(!pulse.set_sink_volume(full, BOOST, None, None).unwrap());
+        // Already at the ceiling, no event would come back to refill the cache in between
+        pulse.increase_sink_volume(INC, None, None, BOOST).unwrap();
+        pulse.increase_sink_volume(INC, None, None, BOOST).unwrap();
+
+        let sink = backend.sink(0).unwrap().unwrap();
+        assert_eq!(2, sink.volume().borrow().len());
+        assert_eq!(100, sink.get_volume_as_pct());
+        assert_eq!(2, get_default(&pulse).borrow().volume().borrow().len());
+        assert_eq!(100, get_default(&pulse).borrow().get_volume_as_pct());
+    }
+
+    #[test]
+    fn test_toggle_mute_keeps_volume_on_the_backend() {
+        let (mut pulse, mut backend) = fake_pulse();
+
+        pulse.toggle_mute(None, None).unwrap();
+
+        let sink = backend.sink(0).unwrap().unwrap();
+        assert!(sink.mute());
+        assert_eq!(50, sink.get_volume_as_pct());
+    }
+
+    #[test]
+    fn test_set_default_sink_moves_streams() {
+        let (mut pulse, mut backend) = fake_pulse();
+
+        pulse
+            .set_default_sink(None, Some("hdmi".to_string()), true)
+            .unwrap();
+
+        assert_eq!("hdmi", backend.default_sink_name());
+        assert_eq!("hdmi", pulse.default_sink_name());
+        assert_eq!(1, backend.sink_input(0).unwrap().unwrap().sink());
+    }
+
+    #[test]
+    fn test_port_and_profile_switches() {
+        let (mut pulse, mut backend) = fake_pulse();
+
+        pulse.set_sink_port(None, None, "headphones").unwrap();
+        pulse.set_card_profile(Some(0), None, "off").unwrap();
+
+        assert_eq!(
+            Some("analog-output-headphones"),
+            backend.sink(0).unwrap().unwrap().active_port()
+        );
+        assert_eq!(
+            Some("off"),
+            backend.card(0).unwrap().unwrap().active_profile()
+        );
+        assert!(pulse.set_sink_port(None, None, "optical").is_err());
+    }
+
+    #[test]
+    fn test_events_keep_the_state_up_to_date() {
+        let (mut pulse, backend) = fake_pulse();
+        let mut seen = Vec::new();
+
+        pulse.subscribe().unwrap();
+        backend.remove_sink(1);
+        backend.add_sink_input(fake_sink_input(7, "mpv", 0));
+        pulse.iterate(true).unwrap();
+        pulse
+            .process_events(|_, event| seen.push(event.kind))
+            .unwrap();
+
+        assert_eq!(vec![ChangeKind::Removed, ChangeKind::New], seen);
+        let manager = pulse.device_manager();
+        assert_eq!(1, manager.borrow_mut().sinks().len());
+        assert_eq!(2, manager.borrow_mut().sink_inputs().len());
+    }
+
+    #[test]
+    fn test_watch_ends_with_the_connection() {
+        let (mut pulse, backend) = fake_pulse();
+        let mut changes = 0;
+
+        backend.remove_sink_input(0);
+        let result = pulse.watch(|_, _| {
+            changes += 1;
+            Ok(())
+        });
+
+        assert!(matches!(result, Err(RupamixError::Connection(_))));
+        assert_eq!(0, changes);
+    }
+
+    // everything below here needs pulseaudio installed, each test starts its own server
+    // with every device at 50%, see TestServer. run with the following flag
+    // --ignored
     //  _ _ _ _ _ _ _ _ _ _
     // _ _ _ _ _ _ _ _ _ _
 
     #[test]
     #[ignore]
-    // This requires volume to be 95 or less, otherwise it will fail
     fn checks_increase_vol_increases_vol() {
-        let mut pulse = setup();
+        let (mut pulse, _server) = setup();
 
         // We are taking our sink here, we need to re-init it later
         let default = get_default(&pulse);
 
         let initial = default.borrow().get_volume_as_pct();
 
-        pulse.increase_sink_volume(&INC, None, None, BOOST);
+        pulse.increase_sink_volume(INC, None, None, BOOST).unwrap();
 
         // re-init so we can get the sync and compare values
-        pulse.update();
+        pulse.update().unwrap();
         let default = get_default(&pulse);
 
         assert_eq!(initial + 5, default.borrow().get_volume_as_pct());
@@ -320,19 +1619,18 @@ mod tests {
 
     #[test]
     #[ignore]
-    // This requires volume to be 5 or greater, otherwise it will fail
     fn checks_decrease_vol_decreases_vol() {
-        let mut pulse = setup();
+        let (mut pulse, _server) = setup();
 
         let default = get_default(&pulse);
 
         let initial = default.borrow().get_volume_as_pct();
 
         //Re-init so that decrease can get the sink
-        pulse.decrease_sink_volume(&5, None, None);
+        pulse.decrease_sink_volume(INC, None, None).unwrap();
 
         // re-init to get the updated system vol
-        pulse.update();
+        pulse.update().unwrap();
         let default = get_default(&pulse);
 
         assert_eq!(initial - 5, default.borrow().get_volume_as_pct());
@@ -341,35 +1639,37 @@ mod tests {
     #[test]
     #[ignore]
     fn checks_toggle_mute_works() {
-        let mut pulse = setup();
+        let (mut pulse, _server) = setup();
 
         let default = get_default(&pulse);
 
         let initial = default.borrow().get_volume_as_pct();
+        let initial_mute = default.borrow().mute();
 
         // Defualt took the sink, re-init
-        pulse.toggle_mute(None, None);
+        pulse.toggle_mute(None, None).unwrap();
 
-        pulse.update();
+        pulse.update().unwrap();
         let default = get_default(&pulse);
 
-        let muted = default.borrow().get_volume_as_pct();
-
-        assert_eq!(muted, 0);
+        // Muting must not touch the volume
+        assert_eq!(!initial_mute, default.borrow().mute());
+        assert_eq!(initial, default.borrow().get_volume_as_pct());
 
         // Re-pop sink list
-        pulse.toggle_mute(None, None);
+        pulse.toggle_mute(None, None).unwrap();
 
-        pulse.update();
+        pulse.update().unwrap();
         let default = get_default(&pulse);
 
+        assert_eq!(initial_mute, default.borrow().mute());
         assert_eq!(initial, default.borrow().get_volume_as_pct());
     }
 
     #[test]
     #[ignore]
     fn checks_set_volume_works() {
-        let mut pulse = setup();
+        let (mut pulse, _server) = setup();
 
         let default = get_default(&pulse);
         let vol = 100;
@@ -378,9 +1678,11 @@ mod tests {
         let initial = default.borrow().get_volume_as_pct();
 
         // Defualt took the sink, re-init
-        pulse.set_sink_volume(vol, boost, None, None);
+        pulse
+            .set_sink_volume(VolumeSpec::Percent(f64::from(vol)), boost, None, None)
+            .unwrap();
 
-        pulse.update();
+        pulse.update().unwrap();
         let default = get_default(&pulse);
 
         let new_vol = default.borrow().get_volume_as_pct();
@@ -388,9 +1690,11 @@ mod tests {
         assert_eq!(vol, new_vol);
 
         // Re-pop sink list
-        pulse.set_sink_volume(initial, boost, None, None);
+        pulse
+            .set_sink_volume(VolumeSpec::Percent(f64::from(initial)), boost, None, None)
+            .unwrap();
 
-        pulse.update();
+        pulse.update().unwrap();
         let default = get_default(&pulse);
 
         assert_eq!(initial, default.borrow().get_volume_as_pct());
@@ -399,7 +1703,7 @@ mod tests {
     #[test]
     #[ignore]
     fn checks_set_volume_works_boosted() {
-        let mut pulse = setup();
+        let (mut pulse, _server) = setup();
 
         let default = get_default(&pulse);
         let vol = 120;
@@ -408,9 +1712,11 @@ mod tests {
         let initial = default.borrow().get_volume_as_pct();
 
         // Defualt took the sink, re-init
-        pulse.set_sink_volume(vol, boost, None, None);
+        pulse
+            .set_sink_volume(VolumeSpec::Percent(f64::from(vol)), boost, None, None)
+            .unwrap();
 
-        pulse.update();
+        pulse.update().unwrap();
         let default = get_default(&pulse);
 
         let new_vol = default.borrow().get_volume_as_pct();
@@ -418,11 +1724,30 @@ mod tests {
         assert_eq!(vol, new_vol);
 
         // Re-pop sink list
-        pulse.set_sink_volume(initial, boost, None, None);
+        pulse
+            .set_sink_volume(VolumeSpec::Percent(f64::from(initial)), boost, None, None)
+            .unwrap();
 
-        pulse.update();
+        pulse.update().unwrap();
         let default = get_default(&pulse);
 
         assert_eq!(initial, default.borrow().get_volume_as_pct());
     }
+
+    #[test]
+    #[ignore]
+    fn checks_default_sink_and_names() {
+        let (mut pulse, _server) = setup();
+
+        assert_eq!(TEST_SINK, pulse.default_sink_name());
+        assert_eq!(50, get_default(&pulse).borrow().get_volume_as_pct());
+
+        pulse
+            .set_default_sink(None, Some(TEST_SINK2.to_string()), false)
+            .unwrap();
+        pulse.update().unwrap();
+
+        assert_eq!(TEST_SINK2, pulse.default_sink_name());
+        assert_eq!(TEST_SINK2, get_default(&pulse).borrow().name());
+    }
 }
@@ -0,0 +1,83 @@
+//! Everything Pulse needs from an audio server. PulseDriver talks to a real one through
+//! libpulse, FakeBackend keeps one in memory so the rest of rupamix can be tested without
+//! touching anyone's speakers.
+//!
+//! Listings hand back our own wrappers rather than the libpulse structs, those only live for
+//! the length of a callback. Changes are by index, the caller looks the index up first.
+use crate::error::RupamixError;
+use crate::pulse_controller::events::ChangeEvent;
+use crate::pulse_wrappers::card_info::PulseCardInfo;
+use crate::pulse_wrappers::server_info::PulseServerInfo;
+use crate::pulse_wrappers::sink_info::PulseSinkInfo;
+use crate::pulse_wrappers::sink_input_info::PulseSinkInputInfo;
+use crate::pulse_wrappers::source_info::PulseSourceInfo;
+use crate::pulse_wrappers::source_output_info::PulseSourceOutputInfo;
+
+use pulse::volume::ChannelVolumes;
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::os::unix::io::RawFd;
+use std::rc::Rc;
+
+pub trait AudioBackend {
+    fn server_info(&mut self) -> Result<PulseServerInfo, RupamixError>;
+
+    fn sinks(&mut self) -> Result<Vec<PulseSinkInfo>, RupamixError>;
+    fn sources(&mut self) -> Result<Vec<PulseSourceInfo>, RupamixError>;
+    fn sink_inputs(&mut self) -> Result<Vec<PulseSinkInputInfo>, RupamixError>;
+    fn source_outputs(&mut self) -> Result<Vec<PulseSourceOutputInfo>, RupamixError>;
+    fn cards(&mut self) -> Result<Vec<PulseCardInfo>, RupamixError>;
+
+    /// The single object lookups return None if the object is gone by the time we ask
+    fn sink(&mut self, index: u32) -> Result<Option<PulseSinkInfo>, RupamixError>;
+    fn source(&mut self, index: u32) -> Result<Option<PulseSourceInfo>, RupamixError>;
+    fn sink_input(&mut self, index: u32) -> Result<Option<PulseSinkInputInfo>, RupamixError>;
+    fn source_output(&mut self, index: u32) -> Result<Option<PulseSourceOutputInfo>, RupamixError>;
+    fn card(&mut self, index: u32) -> Result<Option<PulseCardInfo>, RupamixError>;
+
+    fn set_sink_volume(&mut self, index: u32, volume: &ChannelVolumes) -> Result<(), RupamixError>;
+    fn set_source_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> Result<(), RupamixError>;
+    fn set_sink_input_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> Result<(), RupamixError>;
+    fn set_source_output_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> Result<(), RupamixError>;
+
+    fn set_sink_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError>;
+    fn set_source_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError>;
+    fn set_sink_input_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError>;
+    fn set_source_output_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError>;
+
+    fn set_default_sink(&mut self, name: &str) -> Result<(), RupamixError>;
+    fn set_default_source(&mut self, name: &str) -> Result<(), RupamixError>;
+
+    fn set_sink_port(&mut self, index: u32, port: &str) -> Result<(), RupamixError>;
+    fn set_source_port(&mut self, index: u32, port: &str) -> Result<(), RupamixError>;
+    fn set_card_profile(&mut self, index: u32, profile: &str) -> Result<(), RupamixError>;
+
+    fn move_sink_input(&mut self, index: u32, sink: u32) -> Result<(), RupamixError>;
+    fn move_source_output(&mut self, index: u32, source: u32) -> Result<(), RupamixError>;
+
+    /// Starts pushing change events onto queue. They only arrive while iterate runs.
+    fn subscribe(&mut self, queue: Rc<RefCell<VecDeque<ChangeEvent>>>) -> Result<(), RupamixError>;
+
+    /// Handles whatever the server has sent, blocking until there is something if block is set
+    fn iterate(&mut self, block: bool) -> Result<(), RupamixError>;
+
+    /// From now on a blocking iterate also returns once fd can be read. fd has to stay open
+    /// for as long as the backend is around.
+    fn watch_fd(&mut self, fd: RawFd) -> Result<(), RupamixError>;
+
+    /// Stops watching fd, which has to happen before it is closed
+    fn unwatch_fd(&mut self, fd: RawFd);
+}
@@ -0,0 +1,207 @@
+//! Which server to connect to and how. Without a server libpulse finds one on its own, from
+//! PULSE_SERVER, client.conf or the session, and may start one if there is none.
+//!
+//! Addresses are written the way libpulse takes them:
+//!
+//! - unix:/run/user/1000/pulse/native, or just the path
+//! - tcp:audio-box:4713, tcp:audio-box for the default port, tcp6:[::1]:4713
+use crate::error::RupamixError;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// The port a Pulse server listens on when module-native-protocol-tcp isn't told otherwise
+pub static DEFAULT_PORT: u16 = 4713;
+
+/// How long we wait for the server to be ready before giving up
+pub static DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a single request, i.e. listing the sinks or setting a volume, may take
+pub static DEFAULT_OPERATION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The wait before the first retry, it doubles after each failed attempt up to MAX_RETRY_DELAY
+static FIRST_RETRY_DELAY: Duration = Duration::from_millis(250);
+static MAX_RETRY_DELAY: Duration = Duration::from_secs(4);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ServerAddress {
+    Unix(PathBuf),
+    Tcp { host: String, port: u16 },
+    Tcp6 { host: String, port: u16 },
+}
+
+impl FromStr for ServerAddress {
+    type Err = String;
+
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        let bad = || format!("{address} is not unix:/path, tcp:host:port or tcp6:[host]:port");
+
+        if let Some(path) = address.strip_prefix("unix:").or_else(|| {
+            // A bare path is a socket too
+            address.starts_with('/').then_some(address)
+        }) {
+            if !path.starts_with('/') {
+                return Err(format!("{address}: the socket path must be absolute"));
+            }
+            Ok(ServerAddress::Unix(PathBuf::from(path)))
+        } else if let Some(host) = address.strip_prefix("tcp6:") {
+            let (host, port) = split_ipv6(host).ok_or_else(bad)?;
+            Ok(ServerAddress::Tcp6 { host, port })
+        } else if let Some(host) = address.strip_prefix("tcp:") {
+            let (host, port) = split_port(host).ok_or_else(bad)?;
+            Ok(ServerAddress::Tcp { host, port })
+        } else {
+            Err(bad())
+        }
+    }
+}
+
+impl fmt::Display for ServerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ServerAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+            ServerAddress::Tcp { host, port } => write!(f, "tcp:{host}:{port}"),
+            ServerAddress::Tcp6 { host, port } => write!(f, "tcp6:[{host}]:{port}"),
+        }
+    }
+}
+
+/// host or host:port
+fn split_port(address: &str) -> Option<(String, u16)> {
+    let (host, port) = match address.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (address, DEFAULT_PORT),
+    };
+
+    (!host.is_empty() && !host.contains(':')).then(|| (host.to_string(), port))
+}
+
+/// [host]:port, [host] or a bare host, which can't have a port since it has colons of its own
+fn split_ipv6(address: &str) -> Option<(String, u16)> {
+    let Some(rest) = address.strip_prefix('[') else {
+        return (!address.is_empty()).then(|| (address.to_string(), DEFAULT_PORT));
+    };
+    let (host, port) = rest.split_once(']')?;
+    let port = match port {
+        "" => DEFAULT_PORT,
+        port => port.strip_prefix(':')?.parse().ok()?,
+    };
+
+    (!host.is_empty()).then(|| (host.to_string(), port))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectOptions {
+    /// None lets libpulse pick
+    pub server: Option<ServerAddress>,
+    /// Whether libpulse may start a server if there is none running
+    pub autospawn: bool,
+    pub timeout: Duration,
+    pub operation_timeout: Duration,
+    /// How many more times to try connecting if the server isn't there, i.e. while it restarts
+    pub retries: u32,
+}
+
+impl Default for ConnectOptions {
+    fn default() -> Self {
+        ConnectOptions {
+            server: None,
+            autospawn: true,
+            timeout: DEFAULT_CONNECT_TIMEOUT,
+            operation_timeout: DEFAULT_OPERATION_TIMEOUT,
+            retries: 0,
+        }
+    }
+}
+
+impl ConnectOptions {
+    /// Connects to address with the default flags and timeout
+    pub fn to_server(address: ServerAddress) -> ConnectOptions {
+        ConnectOptions {
+            server: Some(address),
+            ..Default::default()
+        }
+    }
+
+    /// Where we are connecting, for the errors
+    pub fn describe_server(&self) -> String {
+        match &self.server {
+            Some(server) => server.to_string(),
+            None => "the default server".to_string(),
+        }
+    }
+
+    /// How long to wait before retry number attempt, counting from 0
+    pub fn retry_delay(&self, attempt: u32) -> Duration {
+        FIRST_RETRY_DELAY
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(MAX_RETRY_DELAY)
+    }
+
+    pub fn timeout_error(&self) -> RupamixError {
+        RupamixError::Timeout(format!(
+            "Timed out connecting to {} after {:?}",
+            self.describe_server(),
+            self.timeout
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(address: &str) -> ServerAddress {
+        address.parse().unwrap()
+    }
+
+    #[test]
+    fn test_unix_addresses() {
+        let socket = ServerAddress::Unix(PathBuf::from("/run/user/1000/pulse/native"));
+
+        assert_eq!(socket, parse("unix:/run/user/1000/pulse/native"));
+        assert_eq!(socket, parse("/run/user/1000/pulse/native"));
+        assert_eq!("unix:/run/user/1000/pulse/native", socket.to_string());
+        assert!("unix:pulse/native".parse::<ServerAddress>().is_err());
+    }
+
+    #[test]
+    fn test_tcp_addresses() {
+        assert_eq!(
+            ServerAddress::Tcp {
+                host: "audio-box".to_string(),
+                port: 4714
+            },
+            parse("tcp:audio-box:4714")
+        );
+        assert_eq!("tcp:audio-box:4713", parse("tcp:audio-box").to_string());
+        assert_eq!("tcp6:[::1]:4713", parse("tcp6:[::1]").to_string());
+        assert_eq!("tcp6:[::1]:4714", parse("tcp6:[::1]:4714").to_string());
+        assert_eq!("tcp6:[fe80::1]:4713", parse("tcp6:fe80::1").to_string());
+    }
+
+    #[test]
+    fn test_retry_delay_backs_off() {
+        let options = ConnectOptions::default();
+        let delays: Vec<Duration> = (0..6).map(|attempt| options.retry_delay(attempt)).collect();
+
+        assert_eq!(FIRST_RETRY_DELAY, delays[0]);
+        assert_eq!(FIRST_RETRY_DELAY * 2, delays[1]);
+        assert_eq!(MAX_RETRY_DELAY, delays[5]);
+        assert_eq!(MAX_RETRY_DELAY, options.retry_delay(u32::MAX));
+    }
+
+    #[test]
+    fn test_bad_addresses() {
+        for address in [
+            "audio-box",
+            "tcp:",
+            "tcp:box:port",
+            "tcp:box:99999",
+            "tcp6:[::1",
+        ] {
+            assert!(address.parse::<ServerAddress>().is_err(), "{address}");
+        }
+    }
+}
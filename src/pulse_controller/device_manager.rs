@@ -1,43 +1,53 @@
-use pulse::context::introspect::{SinkInfo, SourceInfo};
-
-use crate::pulse_wrappers::device::Device;
+use crate::config::Aliases;
+use crate::pulse_controller::device_pattern::{find_device, find_named, DevicePattern};
+use crate::pulse_wrappers::card_info::PulseCardInfo;
+use crate::pulse_wrappers::device::{Device, VolumeLimits};
 use crate::pulse_wrappers::sink_info::PulseSinkInfo;
+use crate::pulse_wrappers::sink_input_info::PulseSinkInputInfo;
 use crate::pulse_wrappers::source_info::PulseSourceInfo;
+use crate::pulse_wrappers::source_output_info::PulseSourceOutputInfo;
+use crate::pulse_wrappers::volume_spec::VolumeUnit;
 
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
 use std::rc::Rc;
 
 type Sink = Rc<RefCell<PulseSinkInfo>>;
 type Source = Rc<RefCell<PulseSourceInfo>>;
+type SinkInput = Rc<RefCell<PulseSinkInputInfo>>;
+type SourceOutput = Rc<RefCell<PulseSourceOutputInfo>>;
+type Card = Rc<RefCell<PulseCardInfo>>;
 
+#[derive(Debug)]
 pub enum DeviceError {
     NameNotFound(String),
     IndexNotFound(String),
     DefaultNotFound(String),
     NoSinks(String),
+    NoSources(String),
+    NoStreams(String),
+    NoCards(String),
+    ChannelNotFound(String),
+    /// A name pattern that matched more than one device
+    Ambiguous(String),
+    /// A glob or regex that doesn't compile
+    BadPattern(String),
 }
 
-impl DeviceError {
-    /// These errors get passed up for several layers, so it is easiest to
-    /// handle them here.
-    pub fn print_err_and_panic(&self) {
+impl fmt::Display for DeviceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            DeviceError::NameNotFound(e) => {
-                eprintln!("Device NameNotFound Error: {e}");
-                panic!("Unable to continue.")
-            }
-            DeviceError::IndexNotFound(e) => {
-                eprintln!("Device IndexNotFound Error: {e}");
-                panic!("Unable to continue.")
-            }
-            DeviceError::DefaultNotFound(e) => {
-                eprintln!("Device DefaultNotFound Error: {e}");
-                panic!("Unable to continue.")
-            }
-            DeviceError::NoSinks(e) => {
-                eprintln!("Device NoSinks Error: {e}");
-                panic!("Unable to continue.")
-            }
+            DeviceError::NameNotFound(e) => write!(f, "Device NameNotFound Error: {e}"),
+            DeviceError::IndexNotFound(e) => write!(f, "Device IndexNotFound Error: {e}"),
+            DeviceError::DefaultNotFound(e) => write!(f, "Device DefaultNotFound Error: {e}"),
+            DeviceError::NoSinks(e) => write!(f, "Device NoSinks Error: {e}"),
+            DeviceError::NoSources(e) => write!(f, "Device NoSources Error: {e}"),
+            DeviceError::NoStreams(e) => write!(f, "Device NoStreams Error: {e}"),
+            DeviceError::NoCards(e) => write!(f, "Device NoCards Error: {e}"),
+            DeviceError::ChannelNotFound(e) => write!(f, "Device ChannelNotFound Error: {e}"),
+            DeviceError::Ambiguous(e) => write!(f, "Device Ambiguous Error: {e}"),
+            DeviceError::BadPattern(e) => write!(f, "Device BadPattern Error: {e}"),
         }
     }
 }
@@ -46,10 +56,19 @@ impl DeviceError {
 pub struct DeviceManager {
     sources: Vec<Source>,
     sinks: Vec<Sink>,
+    sink_inputs: Vec<SinkInput>,
+    source_outputs: Vec<SourceOutput>,
+    cards: Vec<Card>,
     sources_count: u32,
     sinks_count: u32,
+    sink_inputs_count: u32,
+    source_outputs_count: u32,
+    cards_count: u32,
     default_sink: Option<Sink>,
     default_source: Option<Source>,
+    volume_limits: VolumeLimits,
+    device_limits: HashMap<String, VolumeLimits>,
+    aliases: Aliases,
 }
 
 impl DeviceManager {
@@ -63,6 +82,21 @@ impl DeviceManager {
         &self.sinks
     }
 
+    /// Getter for sink inputs
+    pub fn sink_inputs(&mut self) -> &[SinkInput] {
+        &self.sink_inputs
+    }
+
+    /// Getter for source outputs
+    pub fn source_outputs(&mut self) -> &[SourceOutput] {
+        &self.source_outputs
+    }
+
+    /// Getter for cards
+    pub fn cards(&mut self) -> &[Card] {
+        &self.cards
+    }
+
     /// Getter for default sink
     pub fn default_sink(&mut self) -> Result<Sink, DeviceError> {
         if let Some(default) = self.default_sink.clone() {
@@ -95,34 +129,245 @@ impl DeviceManager {
         self.sinks_count
     }
 
-    /// Method to reset the device manager members to their default values
+    /// Getter for sink inputs count
+    pub fn sink_inputs_count(&self) -> u32 {
+        self.sink_inputs_count
+    }
+
+    /// Getter for cards count
+    pub fn cards_count(&self) -> u32 {
+        self.cards_count
+    }
+
+    /// Getter for source outputs count
+    pub fn source_outputs_count(&self) -> u32 {
+        self.source_outputs_count
+    }
+
+    /// Getter for the volume limits used by devices without limits of their own
+    pub fn volume_limits(&self) -> VolumeLimits {
+        self.volume_limits
+    }
+
+    /// Sets the volume limits for every device and stream that doesn't have its own
+    pub fn set_volume_limits(&mut self, limits: VolumeLimits) {
+        self.volume_limits = limits;
+        self.apply_volume_limits();
+    }
+
+    /// Sets the volume limits of a single sink or source by name. An alias or a pattern, the
+    /// same as --name takes, works too. These are kept across resets, so they still apply to
+    /// the device after the next sync.
+    pub fn set_device_volume_limits(&mut self, name: &str, limits: VolumeLimits) {
+        self.device_limits.insert(name.to_string(), limits);
+        self.apply_volume_limits();
+    }
+
+    /// Lets the aliases from the config stand in for device names. Like the device limits
+    /// these are kept across resets.
+    pub fn set_aliases(&mut self, aliases: Aliases) {
+        self.aliases = aliases;
+        self.apply_volume_limits();
+    }
+
+    /// The limits set for the device's own name, or else for the first alias or pattern, in
+    /// sorted order, that matches it, or else the global ones. aliases are the sinks' or the
+    /// sources' ones.
+    fn limits_for(
+        &self,
+        name: &str,
+        description: &str,
+        aliases: &HashMap<String, String>,
+    ) -> VolumeLimits {
+        if let Some(limits) = self.device_limits.get(name) {
+            return *limits;
+        }
+
+        let mut keys: Vec<&String> = self.device_limits.keys().collect();
+        keys.sort();
+        keys.into_iter()
+            .find(|key| {
+                let target = aliases.get(*key).unwrap_or(key);
+                target == name
+                    || DevicePattern::parse(target)
+                        .is_ok_and(|pattern| pattern.matches(name) || pattern.matches(description))
+            })
+            .map_or(self.volume_limits, |key| self.device_limits[key])
+    }
+
+    fn sink_limits(&self, sink: &PulseSinkInfo) -> VolumeLimits {
+        self.limits_for(sink.name(), sink.description(), &self.aliases.sinks)
+    }
+
+    fn source_limits(&self, source: &PulseSourceInfo) -> VolumeLimits {
+        self.limits_for(source.name(), source.description(), &self.aliases.sources)
+    }
+
+    fn apply_volume_limits(&self) {
+        for sink in &self.sinks {
+            let limits = self.sink_limits(&sink.borrow());
+            sink.borrow_mut().set_limits(limits);
+        }
+        for source in &self.sources {
+            let limits = self.source_limits(&source.borrow());
+            source.borrow_mut().set_limits(limits);
+        }
+        for sink_input in &self.sink_inputs {
+            sink_input.borrow_mut().set_limits(self.volume_limits);
+        }
+        for source_output in &self.source_outputs {
+            source_output.borrow_mut().set_limits(self.volume_limits);
+        }
+    }
+
+    /// Method to reset the device manager members to their default values.
+    /// The volume limits are settings rather than state, so they are left alone.
     pub fn reset(&mut self) {
         self.sinks = Vec::new();
         self.sources = Vec::new();
+        self.sink_inputs = Vec::new();
+        self.source_outputs = Vec::new();
+        self.cards = Vec::new();
         self.default_sink = None;
         self.default_source = None;
         self.sources_count = 0;
         self.sinks_count = 0;
+        self.sink_inputs_count = 0;
+        self.source_outputs_count = 0;
+        self.cards_count = 0;
     }
 
     /// Adds a source into the sources vector and returns the current count
     /// of sources
-    pub fn add_source(&mut self, source_info: &SourceInfo) -> u32 {
-        self.sources
-            .push(Rc::new(RefCell::new(PulseSourceInfo::from(source_info))));
+    pub fn add_source(&mut self, mut source: PulseSourceInfo) -> u32 {
+        source.set_limits(self.source_limits(&source));
+        self.sources.push(Rc::new(RefCell::new(source)));
         self.sources_count += 1;
         self.sources_count
     }
 
     /// Adds a sink into the sinks vector and returns the current count
     /// of sinks
-    pub fn add_sink(&mut self, sink_info: &SinkInfo) -> u32 {
-        self.sinks
-            .push(Rc::new(RefCell::new(PulseSinkInfo::from(sink_info))));
+    pub fn add_sink(&mut self, mut sink: PulseSinkInfo) -> u32 {
+        sink.set_limits(self.sink_limits(&sink));
+        self.sinks.push(Rc::new(RefCell::new(sink)));
         self.sinks_count += 1;
         self.sinks_count
     }
 
+    /// Adds a sink input into the sink inputs vector and returns the current count
+    /// of sink inputs
+    pub fn add_sink_input(&mut self, mut sink_input: PulseSinkInputInfo) -> u32 {
+        sink_input.set_limits(self.volume_limits);
+        self.sink_inputs.push(Rc::new(RefCell::new(sink_input)));
+        self.sink_inputs_count += 1;
+        self.sink_inputs_count
+    }
+
+    /// Adds a source output into the source outputs vector and returns the current count
+    /// of source outputs
+    pub fn add_source_output(&mut self, mut source_output: PulseSourceOutputInfo) -> u32 {
+        source_output.set_limits(self.volume_limits);
+        self.source_outputs
+            .push(Rc::new(RefCell::new(source_output)));
+        self.source_outputs_count += 1;
+        self.source_outputs_count
+    }
+
+    /// Replaces the sink with the same index, or adds it if it is new. The Rc is kept so
+    /// the default sink still points at it.
+    pub fn update_sink(&mut self, mut sink: PulseSinkInfo) {
+        sink.set_limits(self.sink_limits(&sink));
+        if let Some(sink) = replace_by_index(&self.sinks, sink) {
+            self.add_sink(sink);
+        }
+    }
+
+    /// Replaces the source with the same index, or adds it if it is new
+    pub fn update_source(&mut self, mut source: PulseSourceInfo) {
+        source.set_limits(self.source_limits(&source));
+        if let Some(source) = replace_by_index(&self.sources, source) {
+            self.add_source(source);
+        }
+    }
+
+    /// Replaces the sink input with the same index, or adds it if it is new
+    pub fn update_sink_input(&mut self, mut sink_input: PulseSinkInputInfo) {
+        sink_input.set_limits(self.volume_limits);
+        if let Some(sink_input) = replace_by_index(&self.sink_inputs, sink_input) {
+            self.add_sink_input(sink_input);
+        }
+    }
+
+    /// Replaces the source output with the same index, or adds it if it is new
+    pub fn update_source_output(&mut self, mut source_output: PulseSourceOutputInfo) {
+        source_output.set_limits(self.volume_limits);
+        if let Some(source_output) = replace_by_index(&self.source_outputs, source_output) {
+            self.add_source_output(source_output);
+        }
+    }
+
+    /// Adds a card into the cards vector and returns the current count of cards
+    pub fn add_card(&mut self, card: PulseCardInfo) -> u32 {
+        self.cards.push(Rc::new(RefCell::new(card)));
+        self.cards_count += 1;
+        self.cards_count
+    }
+
+    /// Replaces the card with the same index, or adds it if it is new
+    pub fn update_card(&mut self, card: PulseCardInfo) {
+        match self
+            .cards
+            .iter()
+            .find(|existing| existing.borrow().index() == card.index())
+        {
+            Some(existing) => *existing.borrow_mut() = card,
+            None => {
+                self.add_card(card);
+            }
+        }
+    }
+
+    pub fn remove_card(&mut self, index: u32) {
+        self.cards.retain(|card| card.borrow().index() != index);
+        self.cards_count = self.cards.len() as u32;
+    }
+
+    /// Forgets the sink with index, and the default sink if it was that one
+    pub fn remove_sink(&mut self, index: u32) {
+        if let Some(default) = &self.default_sink {
+            if default.borrow().index() == index {
+                self.default_sink = None;
+            }
+        }
+        self.sinks.retain(|sink| sink.borrow().index() != index);
+        self.sinks_count = self.sinks.len() as u32;
+    }
+
+    /// Forgets the source with index, and the default source if it was that one
+    pub fn remove_source(&mut self, index: u32) {
+        if let Some(default) = &self.default_source {
+            if default.borrow().index() == index {
+                self.default_source = None;
+            }
+        }
+        self.sources
+            .retain(|source| source.borrow().index() != index);
+        self.sources_count = self.sources.len() as u32;
+    }
+
+    pub fn remove_sink_input(&mut self, index: u32) {
+        self.sink_inputs
+            .retain(|sink_input| sink_input.borrow().index() != index);
+        self.sink_inputs_count = self.sink_inputs.len() as u32;
+    }
+
+    pub fn remove_source_output(&mut self, index: u32) {
+        self.source_outputs
+            .retain(|source_output| source_output.borrow().index() != index);
+        self.source_outputs_count = self.source_outputs.len() as u32;
+    }
+
     /// Make an RC clone and store it here for easy access to the default source
     pub fn set_default_source(&mut self, name: &str) -> Result<(), DeviceError> {
         for source in self.sources() {
@@ -151,17 +396,11 @@ impl DeviceManager {
         )))
     }
 
-    /// This method attempts to find a sink with the supplied name
+    /// This method attempts to find a sink with the supplied name. Anything that isn't
+    /// an exact name is used as a pattern, see device_pattern for what is accepted
     pub fn get_sink_by_name(&mut self, name: &str) -> Result<Sink, DeviceError> {
-        for sink in self.sinks() {
-            if name == sink.borrow().name() {
-                return Ok(sink.clone());
-            }
-        }
-
-        Err(DeviceError::NameNotFound(format!(
-            "No sink found with name: {name}"
-        )))
+        let name = self.aliases.sinks.get(name).map_or(name, String::as_str);
+        find_device(&self.sinks, name, "sink")
     }
 
     /// This method attempts to find a sink with the supplied index
@@ -172,7 +411,7 @@ impl DeviceManager {
             }
         }
 
-        Err(DeviceError::NameNotFound(format!(
+        Err(DeviceError::IndexNotFound(format!(
             "No sink found with index: {index}"
         )))
     }
@@ -186,34 +425,21 @@ impl DeviceManager {
         index: Option<u32>,
         name: Option<String>,
     ) -> Result<Sink, DeviceError> {
-        let sink;
-        if let Some(index) = index {
-            sink = self.get_sink_by_index(index);
-        } else if let Some(name) = name.clone() {
-            sink = self.get_sink_by_name(&name);
+        if self.sinks.is_empty() {
+            Err(DeviceError::NoSinks("The server has no sinks".to_string()))
+        } else if let Some(index) = index {
+            self.get_sink_by_index(index)
+        } else if let Some(name) = name {
+            self.get_sink_by_name(&name)
         } else {
-            sink = self.default_sink();
-        }
-        if sink.is_ok() {
-            sink
-        } else {
-            Err(DeviceError::NoSinks(format!(
-                "Unable to get a sink for index: {index:?}, name: {name:?}"
-            )))
+            self.default_sink()
         }
     }
 
-    /// This method attempts to find a source with the supplied name
+    /// This method attempts to find a source with the supplied name or pattern
     pub fn get_source_by_name(&mut self, name: &str) -> Result<Source, DeviceError> {
-        for source in self.sources() {
-            if name == source.borrow().name() {
-                return Ok(source.clone());
-            }
-        }
-
-        Err(DeviceError::NameNotFound(format!(
-            "No source found with name: {name}"
-        )))
+        let name = self.aliases.sources.get(name).map_or(name, String::as_str);
+        find_device(&self.sources, name, "source")
     }
 
     /// This method attempts to find a source with the supplied index
@@ -224,18 +450,250 @@ impl DeviceManager {
             }
         }
 
-        Err(DeviceError::NameNotFound(format!(
+        Err(DeviceError::IndexNotFound(format!(
             "No source found with index: {index}"
         )))
     }
 
+    /// The source counterpart of get_sink, see there for how the arguments are used
+    pub fn get_source(
+        &mut self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<Source, DeviceError> {
+        if self.sources.is_empty() {
+            Err(DeviceError::NoSources(
+                "The server has no sources".to_string(),
+            ))
+        } else if let Some(index) = index {
+            self.get_source_by_index(index)
+        } else if let Some(name) = name {
+            self.get_source_by_name(&name)
+        } else {
+            self.default_source()
+        }
+    }
+
+    /// This method attempts to find a card with the supplied index
+    pub fn get_card_by_index(&mut self, index: u32) -> Result<Card, DeviceError> {
+        for card in self.cards() {
+            if index == card.borrow().index() {
+                return Ok(card.clone());
+            }
+        }
+
+        Err(DeviceError::IndexNotFound(format!(
+            "No card found with index: {index}"
+        )))
+    }
+
+    /// This method attempts to find a card with the supplied name or pattern
+    pub fn get_card_by_name(&mut self, name: &str) -> Result<Card, DeviceError> {
+        let card = find_named(&self.cards, name, "card", |card| {
+            let card = card.borrow();
+            (card.name().to_string(), card.description().to_string())
+        })?;
+
+        Ok(card.clone())
+    }
+
+    /// Cards have no default, so one of index or name is needed
+    pub fn get_card(
+        &mut self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<Card, DeviceError> {
+        if self.cards.is_empty() {
+            Err(DeviceError::NoCards("The server has no cards".to_string()))
+        } else if let Some(index) = index {
+            self.get_card_by_index(index)
+        } else if let Some(name) = name {
+            self.get_card_by_name(&name)
+        } else {
+            Err(DeviceError::NameNotFound(
+                "A card index or name is needed".to_string(),
+            ))
+        }
+    }
+
+    /// This method attempts to find a sink input with the supplied index
+    pub fn get_sink_input_by_index(&mut self, index: u32) -> Result<SinkInput, DeviceError> {
+        for sink_input in self.sink_inputs() {
+            if index == sink_input.borrow().index() {
+                return Ok(sink_input.clone());
+            }
+        }
+
+        Err(DeviceError::IndexNotFound(format!(
+            "No stream found with index: {index}"
+        )))
+    }
+
+    /// This method finds every sink input whose application name matches the supplied
+    /// name. The match ignores case since apps are not consistent about it.
+    pub fn get_sink_inputs_by_app_name(
+        &mut self,
+        name: &str,
+    ) -> Result<Vec<SinkInput>, DeviceError> {
+        let found: Vec<SinkInput> = self
+            .sink_inputs()
+            .iter()
+            .filter(|input| input.borrow().application_name().eq_ignore_ascii_case(name))
+            .cloned()
+            .collect();
+
+        if found.is_empty() {
+            Err(DeviceError::NameNotFound(format!(
+                "No stream found with application name: {name}"
+            )))
+        } else {
+            Ok(found)
+        }
+    }
+
+    /// This method finds every sink input owned by the supplied process binary
+    pub fn get_sink_inputs_by_binary(
+        &mut self,
+        binary: &str,
+    ) -> Result<Vec<SinkInput>, DeviceError> {
+        let found: Vec<SinkInput> = self
+            .sink_inputs()
+            .iter()
+            .filter(|input| input.borrow().process_binary() == binary)
+            .cloned()
+            .collect();
+
+        if found.is_empty() {
+            Err(DeviceError::NameNotFound(format!(
+                "No stream found with process binary: {binary}"
+            )))
+        } else {
+            Ok(found)
+        }
+    }
+
+    /// This is a general method that accepts three optional arguments: index, app, binary
+    /// The first one supplied, in that order, is used to select the sink inputs.
+    /// Unlike sinks, streams have no default, so one of them must be given.
+    pub fn get_sink_inputs(
+        &mut self,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<Vec<SinkInput>, DeviceError> {
+        if let Some(index) = index {
+            Ok(vec![self.get_sink_input_by_index(index)?])
+        } else if let Some(app) = app {
+            self.get_sink_inputs_by_app_name(&app)
+        } else if let Some(binary) = binary {
+            self.get_sink_inputs_by_binary(&binary)
+        } else {
+            Err(DeviceError::NoStreams(
+                "A stream index, application name or binary is required".to_string(),
+            ))
+        }
+    }
+
+    /// This method attempts to find a source output with the supplied index
+    pub fn get_source_output_by_index(&mut self, index: u32) -> Result<SourceOutput, DeviceError> {
+        for source_output in self.source_outputs() {
+            if index == source_output.borrow().index() {
+                return Ok(source_output.clone());
+            }
+        }
+
+        Err(DeviceError::IndexNotFound(format!(
+            "No recording stream found with index: {index}"
+        )))
+    }
+
+    /// This method finds every source output whose application name matches the supplied
+    /// name. The match ignores case since apps are not consistent about it.
+    pub fn get_source_outputs_by_app_name(
+        &mut self,
+        name: &str,
+    ) -> Result<Vec<SourceOutput>, DeviceError> {
+        let found: Vec<SourceOutput> = self
+            .source_outputs()
+            .iter()
+            .filter(|output| {
+                output
+                    .borrow()
+                    .application_name()
+                    .eq_ignore_ascii_case(name)
+            })
+            .cloned()
+            .collect();
+
+        if found.is_empty() {
+            Err(DeviceError::NameNotFound(format!(
+                "No recording stream found with application name: {name}"
+            )))
+        } else {
+            Ok(found)
+        }
+    }
+
+    /// This method finds every source output owned by the supplied process binary
+    pub fn get_source_outputs_by_binary(
+        &mut self,
+        binary: &str,
+    ) -> Result<Vec<SourceOutput>, DeviceError> {
+        let found: Vec<SourceOutput> = self
+            .source_outputs()
+            .iter()
+            .filter(|output| output.borrow().process_binary() == binary)
+            .cloned()
+            .collect();
+
+        if found.is_empty() {
+            Err(DeviceError::NameNotFound(format!(
+                "No recording stream found with process binary: {binary}"
+            )))
+        } else {
+            Ok(found)
+        }
+    }
+
+    /// The recording counterpart of get_sink_inputs, see there for how the arguments are used
+    pub fn get_source_outputs(
+        &mut self,
+        index: Option<u32>,
+        app: Option<String>,
+        binary: Option<String>,
+    ) -> Result<Vec<SourceOutput>, DeviceError> {
+        if let Some(index) = index {
+            Ok(vec![self.get_source_output_by_index(index)?])
+        } else if let Some(app) = app {
+            self.get_source_outputs_by_app_name(&app)
+        } else if let Some(binary) = binary {
+            self.get_source_outputs_by_binary(&binary)
+        } else {
+            Err(DeviceError::NoStreams(
+                "A stream index, application name or binary is required".to_string(),
+            ))
+        }
+    }
+
     pub fn print_sink_volume(
         &mut self,
         index: Option<u32>,
         name: Option<String>,
+        unit: VolumeUnit,
     ) -> Result<(), DeviceError> {
         let sink = self.get_sink(index, name)?;
-        sink.borrow().print_volume();
+        sink.borrow().print_volume(unit);
+        Ok(())
+    }
+
+    pub fn print_source_volume(
+        &mut self,
+        index: Option<u32>,
+        name: Option<String>,
+        unit: VolumeUnit,
+    ) -> Result<(), DeviceError> {
+        let source = self.get_source(index, name)?;
+        source.borrow().print_volume(unit);
         Ok(())
     }
 
@@ -282,6 +740,7 @@ impl DeviceManager {
                     source.borrow().name()
                 );
             }
+            source.borrow().print_ports(len_idx);
         }
         Ok(())
     }
@@ -326,13 +785,187 @@ impl DeviceManager {
                     sink.borrow().name()
                 );
             }
+            sink.borrow().print_ports(len_idx);
         }
         Ok(())
     }
+
+    /// This will print to the comand line the sink inputs in the following format
+    //   index -- sink -- volume -- application
+    // ------------------------------------------
+    //      10 --    1 --    54% -- AppA
+    //      12 --    2 --  (mute) -- AppB
+    pub fn print_sink_inputs(&mut self) -> Result<(), DeviceError> {
+        if self.sink_inputs().is_empty() {
+            return Err(DeviceError::NoStreams(
+                "No application is currently playing audio".to_string(),
+            ));
+        }
+
+        let mut len_idx = "Index".len();
+        let mut len_app = "Application".len();
+
+        for input in self.sink_inputs() {
+            let len = input.borrow().index().to_string().len();
+            if len > len_idx {
+                len_idx = len;
+            }
+            let len = input.borrow().application_name().len();
+            if len > len_app {
+                len_app = len;
+            }
+        }
+
+        let sum = len_idx + len_app + 22; // three ' -- ' plus the sink and volume columns
+
+        println!();
+        println!(
+            "{:>len_idx$} -- {:>4} -- {:>6} -- {:<len_app$}",
+            "Index", "Sink", "Volume", "Application"
+        );
+        println!("{:-<sum$}", "");
+        for input in self.sink_inputs() {
+            let input = input.borrow();
+            let volume = if input.mute() {
+                "(mute)".to_string()
+            } else {
+                format!("{}%", input.get_volume_as_pct())
+            };
+            println!(
+                "{:>len_idx$} -- {:>4} -- {:>6} -- {:<len_app$}",
+                input.index(),
+                input.sink(),
+                volume,
+                input.application_name()
+            );
+        }
+        Ok(())
+    }
+
+    /// This will print to the comand line the source outputs in the following format
+    //   index -- source -- volume -- application
+    // --------------------------------------------
+    //      10 --      1 --    54% -- AppA
+    //      12 --      2 -- (mute) -- AppB
+    pub fn print_source_outputs(&mut self) -> Result<(), DeviceError> {
+        if self.source_outputs().is_empty() {
+            return Err(DeviceError::NoStreams(
+                "No application is currently recording audio".to_string(),
+            ));
+        }
+
+        let mut len_idx = "Index".len();
+        let mut len_app = "Application".len();
+
+        for output in self.source_outputs() {
+            let len = output.borrow().index().to_string().len();
+            if len > len_idx {
+                len_idx = len;
+            }
+            let len = output.borrow().application_name().len();
+            if len > len_app {
+                len_app = len;
+            }
+        }
+
+        let sum = len_idx + len_app + 24; // three ' -- ' plus the source and volume columns
+
+        println!();
+        println!(
+            "{:>len_idx$} -- {:>6} -- {:>6} -- {:<len_app$}",
+            "Index", "Source", "Volume", "Application"
+        );
+        println!("{:-<sum$}", "");
+        for output in self.source_outputs() {
+            let output = output.borrow();
+            let volume = if output.mute() {
+                "(mute)".to_string()
+            } else {
+                format!("{}%", output.get_volume_as_pct())
+            };
+            println!(
+                "{:>len_idx$} -- {:>6} -- {:>6} -- {:<len_app$}",
+                output.index(),
+                output.source(),
+                volume,
+                output.application_name()
+            );
+        }
+        Ok(())
+    }
+
+    /// Prints each card with its profiles underneath, the active one marked with a *
+    pub fn print_cards(&mut self) -> Result<(), DeviceError> {
+        if self.cards().is_empty() {
+            return Err(DeviceError::NoCards("The server has no cards".to_string()));
+        }
+
+        let mut len_idx = "Index".len();
+        let mut len_name = "Name".len();
+
+        for card in self.cards() {
+            let len = card.borrow().index().to_string().len();
+            if len > len_idx {
+                len_idx = len;
+            }
+            let len = card.borrow().name().len();
+            if len > len_name {
+                len_name = len;
+            }
+        }
+
+        let sum = len_idx + len_name + 4;
+
+        println!();
+        println!("{:>len_idx$} -- {:<len_name$}", "Index", "Name");
+        println!("{:-<sum$}", "");
+        for card in self.cards() {
+            let card = card.borrow();
+            println!(
+                "{:>len_idx$} -- {:<len_name$} ({})",
+                card.index(),
+                card.name(),
+                card.description()
+            );
+            for profile in card.profiles() {
+                let active = if card.active_profile() == Some(profile.name.as_str()) {
+                    "*"
+                } else {
+                    " "
+                };
+                let available = if profile.available {
+                    ""
+                } else {
+                    " (unavailable)"
+                };
+                println!(
+                    "{:>len_idx$}    {active} {} -- {}{available}",
+                    "", profile.name, profile.description
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Swaps the contents of the Rc with a matching index, hands the device back if there was none
+fn replace_by_index<T: Device<T>>(devices: &[Rc<RefCell<T>>], device: T) -> Option<T> {
+    match devices
+        .iter()
+        .find(|dev| dev.borrow().index() == device.index())
+    {
+        Some(existing) => {
+            *existing.borrow_mut() = device;
+            None
+        }
+        None => Some(device),
+    }
 }
 
 #[cfg(test)]
 mod tets {
+    use crate::pulse_wrappers::port_info::{PortAvailability, PulsePortInfo};
+    use pulse::channelmap::{Map, Position};
     use pulse::volume::{ChannelVolumes, Volume, VolumeDB};
 
     use super::*;
@@ -341,6 +974,8 @@ mod tets {
     static DESC: &str = "test desc";
     static IDX: u32 = 150;
     static CHANNELS: u8 = 2;
+    static APP: &str = "Test App";
+    static BINARY: &str = "test-app";
 
     impl DeviceManager {
         fn mock_add_source(&mut self, source: PulseSourceInfo) -> u32 {
@@ -349,24 +984,62 @@ mod tets {
             self.sources_count
         }
 
+        fn mock_add_card(&mut self, card: PulseCardInfo) -> u32 {
+            self.cards.push(Rc::new(RefCell::new(card)));
+            self.cards_count += 1;
+            self.cards_count
+        }
+
         fn mock_add_sink(&mut self, sink: PulseSinkInfo) -> u32 {
             self.sinks.push(Rc::new(RefCell::new(sink)));
             self.sinks_count += 1;
             self.sinks_count
         }
+
+        fn mock_add_sink_input(&mut self, sink_input: PulseSinkInputInfo) -> u32 {
+            self.sink_inputs.push(Rc::new(RefCell::new(sink_input)));
+            self.sink_inputs_count += 1;
+            self.sink_inputs_count
+        }
+
+        fn mock_add_source_output(&mut self, source_output: PulseSourceOutputInfo) -> u32 {
+            self.source_outputs
+                .push(Rc::new(RefCell::new(source_output)));
+            self.source_outputs_count += 1;
+            self.source_outputs_count
+        }
+    }
+
+    fn stereo_map() -> Map {
+        let mut map = Map::default();
+        map.set_len(CHANNELS);
+        map.get_mut()[0] = Position::FrontLeft;
+        map.get_mut()[1] = Position::FrontRight;
+        map
     }
 
     fn setup_sink() -> PulseSinkInfo {
-        let name = NAME.to_string();
-        let index = IDX;
-        let description = DESC.to_string();
+        setup_named_sink(IDX, NAME, DESC)
+    }
+
+    fn setup_named_sink(index: u32, name: &str, description: &str) -> PulseSinkInfo {
+        let name = name.to_string();
+        let description = description.to_string();
         let base_volume = Volume::from(APPRROX_54_PCT);
         let mut volume = ChannelVolumes::default();
         volume.set(CHANNELS, base_volume);
 
         let base_volume = Rc::new(RefCell::new(base_volume));
         let volume = Rc::new(RefCell::new(volume));
-        PulseSinkInfo::new(name, index, description, volume, base_volume)
+        PulseSinkInfo::new(
+            name,
+            index,
+            description,
+            volume,
+            base_volume,
+            false,
+            stereo_map(),
+        )
     }
 
     fn setup_source() -> PulseSourceInfo {
@@ -379,7 +1052,60 @@ mod tets {
 
         let base_volume = Rc::new(RefCell::new(base_volume));
         let volume = Rc::new(RefCell::new(volume));
-        PulseSourceInfo::new(name, index, description, volume, base_volume)
+        PulseSourceInfo::new(
+            name,
+            index,
+            description,
+            volume,
+            base_volume,
+            false,
+            stereo_map(),
+        )
+    }
+
+    fn setup_sink_input(index: u32, app: &str, binary: &str) -> PulseSinkInputInfo {
+        let mut volume = ChannelVolumes::default();
+        volume.set(CHANNELS, Volume::from(APPRROX_54_PCT));
+
+        let volume = Rc::new(RefCell::new(volume));
+        PulseSinkInputInfo::new(
+            NAME.to_string(),
+            index,
+            app.to_string(),
+            binary.to_string(),
+            IDX,
+            false,
+            volume,
+            stereo_map(),
+        )
+    }
+
+    fn setup_source_output(index: u32, app: &str, binary: &str) -> PulseSourceOutputInfo {
+        let mut volume = ChannelVolumes::default();
+        volume.set(CHANNELS, Volume::from(APPRROX_54_PCT));
+
+        let volume = Rc::new(RefCell::new(volume));
+        PulseSourceOutputInfo::new(
+            NAME.to_string(),
+            index,
+            app.to_string(),
+            binary.to_string(),
+            IDX,
+            false,
+            volume,
+            stereo_map(),
+        )
+    }
+
+    fn setup_card(index: u32, name: &str, description: &str) -> PulseCardInfo {
+        PulseCardInfo::new(
+            name.to_string(),
+            index,
+            description.to_string(),
+            "module-test".to_string(),
+            Vec::new(),
+            None,
+        )
     }
 
     fn setup_manager() -> DeviceManager {
@@ -389,6 +1115,9 @@ mod tets {
 
         manager.mock_add_source(source);
         manager.mock_add_sink(sink);
+        manager.mock_add_sink_input(setup_sink_input(IDX, APP, BINARY));
+        manager.mock_add_sink_input(setup_sink_input(IDX + 1, APP, BINARY));
+        manager.mock_add_source_output(setup_source_output(IDX, APP, BINARY));
         manager
     }
 
@@ -479,4 +1208,249 @@ mod tets {
 
         assert_eq!(NAME, default.ok().unwrap().borrow().name());
     }
+
+    #[test]
+    fn test_get_sink_input_by_idx() {
+        let mut manager = setup_manager();
+
+        let sink_input = manager.get_sink_input_by_index(IDX + 1);
+
+        assert!(sink_input.is_ok());
+        assert_eq!(sink_input.ok().unwrap().borrow().index(), IDX + 1);
+    }
+
+    #[test]
+    fn test_get_sink_inputs_by_app_name_ignores_case() {
+        let mut manager = setup_manager();
+
+        let sink_inputs = manager.get_sink_inputs_by_app_name(&APP.to_uppercase());
+
+        assert!(sink_inputs.is_ok());
+        assert_eq!(sink_inputs.ok().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_get_sink_inputs_by_binary() {
+        let mut manager = setup_manager();
+
+        let sink_inputs = manager.get_sink_inputs(None, None, Some(BINARY.to_string()));
+
+        assert!(sink_inputs.is_ok());
+        assert_eq!(sink_inputs.ok().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_get_sink_inputs_requires_a_selector() {
+        let mut manager = setup_manager();
+
+        let sink_inputs = manager.get_sink_inputs(None, None, None);
+
+        assert!(matches!(sink_inputs, Err(DeviceError::NoStreams(_))));
+    }
+
+    #[test]
+    fn test_get_source_output_by_idx() {
+        let mut manager = setup_manager();
+
+        let source_output = manager.get_source_output_by_index(IDX);
+
+        assert!(source_output.is_ok());
+        assert_eq!(source_output.ok().unwrap().borrow().index(), IDX);
+    }
+
+    #[test]
+    fn test_get_source_outputs_by_app_name() {
+        let mut manager = setup_manager();
+
+        let source_outputs = manager.get_source_outputs(None, Some(APP.to_string()), None);
+
+        assert!(source_outputs.is_ok());
+        assert_eq!(source_outputs.ok().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_get_source_falls_back_to_default() {
+        let mut manager = setup_manager();
+        manager.set_default_source(NAME).ok().unwrap();
+
+        let source = manager.get_source(None, None);
+
+        assert!(source.is_ok());
+        assert_eq!(source.ok().unwrap().borrow().name(), NAME);
+    }
+
+    #[test]
+    fn test_get_sink_keeps_the_lookup_error() {
+        let mut manager = setup_manager();
+
+        let sink = manager.get_sink(Some(IDX + 100), None);
+
+        assert!(matches!(sink, Err(DeviceError::IndexNotFound(_))));
+    }
+
+    #[test]
+    fn test_get_source_keeps_the_lookup_error() {
+        let mut manager = setup_manager();
+
+        let source = manager.get_source(Some(IDX + 100), None);
+
+        assert!(matches!(source, Err(DeviceError::IndexNotFound(_))));
+    }
+
+    #[test]
+    fn test_get_source_without_sources() {
+        let mut manager = DeviceManager::default();
+
+        let source = manager.get_source(None, None);
+
+        assert!(matches!(source, Err(DeviceError::NoSources(_))));
+    }
+
+    #[test]
+    fn test_get_sink_by_pattern() {
+        let mut manager = setup_manager();
+        manager.mock_add_sink(setup_named_sink(
+            IDX + 1,
+            "alsa_output.usb-Audient_iD4-00.HiFi__hw_iD4__sink",
+            "iD4 Analog Stereo",
+        ));
+        manager.mock_add_sink(setup_named_sink(
+            IDX + 2,
+            "alsa_output.pci-0000_0b_00.4.hdmi-stereo",
+            "HDMI Analog Stereo",
+        ));
+
+        let by_substring = manager.get_sink(None, Some("audient".to_string()));
+        let by_description = manager.get_sink(None, Some("hdmi analog".to_string()));
+        let ambiguous = manager.get_sink(None, Some("alsa_output.*".to_string()));
+        let missing = manager.get_sink(None, Some("bluez".to_string()));
+
+        assert_eq!(IDX + 1, by_substring.ok().unwrap().borrow().index());
+        assert_eq!(IDX + 2, by_description.ok().unwrap().borrow().index());
+        assert!(matches!(ambiguous, Err(DeviceError::Ambiguous(e)) if e.contains("hdmi-stereo")));
+        assert!(matches!(missing, Err(DeviceError::NameNotFound(_))));
+    }
+
+    #[test]
+    fn test_aliases_stand_in_for_names() {
+        let mut manager = setup_manager();
+        let mut aliases = Aliases::default();
+        aliases
+            .sinks
+            .insert("speakers".to_string(), NAME.to_string());
+        manager.set_aliases(aliases);
+
+        let sink = manager.get_sink(None, Some("speakers".to_string()));
+        let source = manager.get_source(None, Some("speakers".to_string()));
+
+        assert_eq!(NAME, sink.ok().unwrap().borrow().name());
+        assert!(matches!(source, Err(DeviceError::NameNotFound(_))));
+    }
+
+    #[test]
+    fn test_get_card_by_pattern() {
+        let mut manager = setup_manager();
+        manager.mock_add_card(setup_card(1, "bluez_card.00_1B_66_A1_2C_3D", "WH-1000XM4"));
+        manager.mock_add_card(setup_card(
+            2,
+            "alsa_card.pci-0000_0b_00.4",
+            "Starship/Matisse",
+        ));
+
+        let by_description = manager.get_card(None, Some("xm4".to_string()));
+        let by_index = manager.get_card(Some(2), None);
+        let missing = manager.get_card(Some(3), None);
+
+        assert_eq!(1, by_description.ok().unwrap().borrow().index());
+        assert_eq!(
+            "Starship/Matisse",
+            by_index.ok().unwrap().borrow().description()
+        );
+        assert!(matches!(missing, Err(DeviceError::IndexNotFound(_))));
+    }
+
+    #[test]
+    fn test_get_card_without_cards() {
+        let mut manager = DeviceManager::default();
+
+        let card = manager.get_card(Some(1), None);
+
+        assert!(matches!(card, Err(DeviceError::NoCards(_))));
+    }
+
+    #[test]
+    fn test_find_port() {
+        let mut sink = setup_sink();
+        let port = |name: &str, description: &str, availability| PulsePortInfo {
+            name: name.to_string(),
+            description: description.to_string(),
+            priority: 0,
+            availability,
+        };
+        sink.set_ports(
+            vec![
+                port(
+                    "analog-output-speaker",
+                    "Speakers",
+                    PortAvailability::Unknown,
+                ),
+                port(
+                    "analog-output-headphones",
+                    "Headphones",
+                    PortAvailability::Plugged,
+                ),
+            ],
+            Some("analog-output-speaker".to_string()),
+        );
+
+        assert_eq!(
+            "analog-output-headphones",
+            sink.find_port("headphones").unwrap().name
+        );
+        assert!(matches!(
+            sink.find_port("analog-*"),
+            Err(DeviceError::Ambiguous(_))
+        ));
+        assert!(matches!(
+            setup_source().find_port("headphones"),
+            Err(DeviceError::NameNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_device_limits_override_global_limits() {
+        let mut manager = setup_manager();
+        let global = VolumeLimits::new(90.0, 150.0, false);
+        let device = VolumeLimits::new(80.0, 100.0, false);
+
+        manager.set_volume_limits(global);
+        manager.set_device_volume_limits(NAME, device);
+
+        let sink = manager.get_sink_by_name(NAME).ok().unwrap();
+        assert_eq!(sink.borrow().limits(), device);
+        let sink_input = manager.get_sink_input_by_index(IDX).ok().unwrap();
+        assert_eq!(sink_input.borrow().limits(), global);
+    }
+
+    #[test]
+    fn test_remove_default_sink() {
+        let mut manager = setup_manager();
+        manager.set_default_sink(NAME).ok().unwrap();
+
+        manager.remove_sink(IDX);
+
+        assert_eq!(manager.sinks_count(), 0);
+        assert!(manager.default_sink().is_err());
+    }
+
+    #[test]
+    fn test_remove_sink_input() {
+        let mut manager = setup_manager();
+
+        manager.remove_sink_input(IDX);
+
+        assert_eq!(manager.sink_inputs_count(), 1);
+        assert!(manager.get_sink_input_by_index(IDX).is_err());
+        assert!(manager.get_sink_input_by_index(IDX + 1).is_ok());
+    }
 }
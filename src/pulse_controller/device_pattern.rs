@@ -0,0 +1,181 @@
+//! Picks devices by a short pattern instead of their full name. A pattern is matched against
+//! both the name and the description, ignoring case, and can be:
+//!
+//! - a plain substring, i.e. "audient" or "hdmi"
+//! - a glob if it has a *, ? or [ in it, i.e. "alsa_output.usb-*"
+//! - a regex if it starts with "re:", i.e. "re:^bluez_.*a2dp"
+//!
+//! An exact name always wins, so a device whose name happens to be a substring of another
+//! device's can still be picked.
+use crate::pulse_controller::device_manager::DeviceError;
+use crate::pulse_wrappers::device::Device;
+use regex::{Regex, RegexBuilder};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+static REGEX_PREFIX: &str = "re:";
+
+#[derive(Debug, Clone)]
+pub enum DevicePattern {
+    Substring(String),
+    Glob(Regex),
+    Regex(Regex),
+}
+
+impl DevicePattern {
+    pub fn parse(pattern: &str) -> Result<DevicePattern, DeviceError> {
+        if let Some(regex) = pattern.strip_prefix(REGEX_PREFIX) {
+            Ok(DevicePattern::Regex(build_regex(regex, pattern)?))
+        } else if pattern.contains(['*', '?', '[']) {
+            Ok(DevicePattern::Glob(build_regex(
+                &glob_to_regex(pattern),
+                pattern,
+            )?))
+        } else {
+            Ok(DevicePattern::Substring(pattern.to_lowercase()))
+        }
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            DevicePattern::Substring(needle) => text.to_lowercase().contains(needle),
+            DevicePattern::Glob(regex) | DevicePattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// Finds the one device the pattern points at. kind is only used in the errors, i.e. "sink"
+pub fn find_device<T, D>(
+    devices: &[Rc<RefCell<D>>],
+    pattern: &str,
+    kind: &str,
+) -> Result<Rc<RefCell<D>>, DeviceError>
+where
+    D: Device<T>,
+{
+    let device = find_named(devices, pattern, kind, |device| {
+        let device = device.borrow();
+        (device.name().to_string(), device.description().to_string())
+    })?;
+
+    Ok(device.clone())
+}
+
+/// The same search as find_device for things that aren't devices, i.e. cards and profiles.
+/// names gives the name and description of an item.
+pub fn find_named<'a, D, F>(
+    items: &'a [D],
+    pattern: &str,
+    kind: &str,
+    names: F,
+) -> Result<&'a D, DeviceError>
+where
+    F: Fn(&D) -> (String, String),
+{
+    if let Some(item) = items.iter().find(|item| names(item).0 == pattern) {
+        return Ok(item);
+    }
+
+    let matcher = DevicePattern::parse(pattern)?;
+    let candidates: Vec<(&D, String, String)> = items
+        .iter()
+        .map(|item| {
+            let (name, description) = names(item);
+            (item, name, description)
+        })
+        .filter(|(_, name, description)| matcher.matches(name) || matcher.matches(description))
+        .collect();
+
+    match candidates.as_slice() {
+        [(item, _, _)] => Ok(item),
+        [] => Err(DeviceError::NameNotFound(format!(
+            "No {kind} matches: {pattern}"
+        ))),
+        _ => {
+            let list: Vec<String> = candidates
+                .iter()
+                .map(|(_, name, description)| format!("  {name} ({description})"))
+                .collect();
+            Err(DeviceError::Ambiguous(format!(
+                "{pattern} matches more than one {kind}, be more specific:\n{}",
+                list.join("\n")
+            )))
+        }
+    }
+}
+
+/// Globs match the whole name, only * ? and [..] are special
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut in_class = false;
+
+    for c in glob.chars() {
+        match c {
+            '*' if !in_class => regex.push_str(".*"),
+            '?' if !in_class => regex.push('.'),
+            '[' if !in_class => {
+                in_class = true;
+                regex.push('[');
+            }
+            ']' if in_class => {
+                in_class = false;
+                regex.push(']');
+            }
+            '!' if in_class && regex.ends_with('[') => regex.push('^'),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex.push('$');
+    regex
+}
+
+fn build_regex(regex: &str, pattern: &str) -> Result<Regex, DeviceError> {
+    RegexBuilder::new(regex)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| DeviceError::BadPattern(format!("{pattern}: {e}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_picks_the_kind() {
+        assert!(matches!(
+            DevicePattern::parse("iD4"),
+            Ok(DevicePattern::Substring(_))
+        ));
+        assert!(matches!(
+            DevicePattern::parse("alsa_output.usb-*"),
+            Ok(DevicePattern::Glob(_))
+        ));
+        assert!(matches!(
+            DevicePattern::parse("re:^bluez"),
+            Ok(DevicePattern::Regex(_))
+        ));
+        assert!(matches!(
+            DevicePattern::parse("re:("),
+            Err(DeviceError::BadPattern(_))
+        ));
+    }
+
+    #[test]
+    fn test_matching_ignores_case() {
+        let name = "alsa_output.usb-Audient_iD4-00.HiFi__hw_iD4__sink";
+
+        assert!(DevicePattern::parse("audient").unwrap().matches(name));
+        assert!(DevicePattern::parse("*usb-audient*").unwrap().matches(name));
+        assert!(DevicePattern::parse("re:id4__sink$").unwrap().matches(name));
+        assert!(!DevicePattern::parse("usb-*").unwrap().matches(name));
+    }
+
+    #[test]
+    fn test_glob_classes() {
+        let glob = DevicePattern::parse("hdmi-[!0]").unwrap();
+
+        assert!(glob.matches("hdmi-1"));
+        assert!(!glob.matches("hdmi-0"));
+    }
+}
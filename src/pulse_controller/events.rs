@@ -0,0 +1,120 @@
+//! The subset of Pulse's subscribe events we care about. Pulse hands these to us as a
+//! facility, an operation and the index of the object that changed.
+use pulse::context::subscribe::{Facility, Operation};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeFacility {
+    Sink,
+    Source,
+    SinkInput,
+    SourceOutput,
+    Card,
+    Server,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    New,
+    Changed,
+    Removed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ChangeEvent {
+    pub facility: ChangeFacility,
+    pub kind: ChangeKind,
+    pub index: u32,
+}
+
+impl ChangeEvent {
+    /// None for the facilities we don't watch, i.e. modules and clients
+    pub fn from_pulse(
+        facility: Option<Facility>,
+        operation: Option<Operation>,
+        index: u32,
+    ) -> Option<ChangeEvent> {
+        let facility = match facility? {
+            Facility::Sink => ChangeFacility::Sink,
+            Facility::Source => ChangeFacility::Source,
+            Facility::SinkInput => ChangeFacility::SinkInput,
+            Facility::SourceOutput => ChangeFacility::SourceOutput,
+            Facility::Card => ChangeFacility::Card,
+            Facility::Server => ChangeFacility::Server,
+            _ => return None,
+        };
+        let kind = match operation? {
+            Operation::New => ChangeKind::New,
+            Operation::Changed => ChangeKind::Changed,
+            Operation::Removed => ChangeKind::Removed,
+        };
+
+        Some(ChangeEvent {
+            facility,
+            kind,
+            index,
+        })
+    }
+}
+
+impl fmt::Display for ChangeFacility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let facility = match self {
+            ChangeFacility::Sink => "sink",
+            ChangeFacility::Source => "source",
+            ChangeFacility::SinkInput => "sink-input",
+            ChangeFacility::SourceOutput => "source-output",
+            ChangeFacility::Card => "card",
+            ChangeFacility::Server => "server",
+        };
+        write!(f, "{facility}")
+    }
+}
+
+impl fmt::Display for ChangeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self {
+            ChangeKind::New => "new",
+            ChangeKind::Changed => "changed",
+            ChangeKind::Removed => "removed",
+        };
+        write!(f, "{kind}")
+    }
+}
+
+/// i.e. "changed sink #1"
+impl fmt::Display for ChangeEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} #{}", self.kind, self.facility, self.index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_pulse() {
+        let event = ChangeEvent::from_pulse(Some(Facility::SinkInput), Some(Operation::New), 42);
+
+        assert_eq!(
+            Some(ChangeEvent {
+                facility: ChangeFacility::SinkInput,
+                kind: ChangeKind::New,
+                index: 42,
+            }),
+            event
+        );
+        assert_eq!("new sink-input #42", event.unwrap().to_string());
+    }
+
+    #[test]
+    fn test_ignores_unwatched_facilities() {
+        let event = ChangeEvent::from_pulse(Some(Facility::Client), Some(Operation::New), 1);
+
+        assert_eq!(None, event);
+    }
+}
@@ -0,0 +1,713 @@
+//! An audio server that only lives in memory, so Pulse and the commands on top of it can be
+//! tested without a running server. Clones share the same server, keep one around to look at
+//! after handing another to Pulse::with_backend.
+//!
+//! Changes made through AudioBackend queue up events the same way a real server would: only
+//! when something actually changed, and only arriving with a later iterate, after the change
+//! itself has been answered.
+use crate::error::RupamixError;
+use crate::pulse_controller::backend::AudioBackend;
+use crate::pulse_controller::events::{ChangeEvent, ChangeFacility, ChangeKind};
+use crate::pulse_wrappers::card_info::{PulseCardInfo, PulseCardProfile};
+use crate::pulse_wrappers::device::Device;
+use crate::pulse_wrappers::port_info::{PortAvailability, PulsePortInfo};
+use crate::pulse_wrappers::server_info::PulseServerInfo;
+use crate::pulse_wrappers::sink_info::PulseSinkInfo;
+use crate::pulse_wrappers::sink_input_info::PulseSinkInputInfo;
+use crate::pulse_wrappers::source_info::PulseSourceInfo;
+use crate::pulse_wrappers::source_output_info::PulseSourceOutputInfo;
+
+use pulse::channelmap::{Map, Position};
+use pulse::volume::{ChannelVolumes, Volume};
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::os::unix::io::RawFd;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Default)]
+pub struct FakeBackend {
+    server: Rc<RefCell<FakeServer>>,
+}
+
+#[derive(Default)]
+struct FakeServer {
+    server_info: PulseServerInfo,
+    sinks: Vec<PulseSinkInfo>,
+    sources: Vec<PulseSourceInfo>,
+    sink_inputs: Vec<PulseSinkInputInfo>,
+    source_outputs: Vec<PulseSourceOutputInfo>,
+    cards: Vec<PulseCardInfo>,
+    /// Events on their way, they arrive with the next iterate
+    in_flight: VecDeque<ChangeEvent>,
+    /// Events that have arrived, the next iterate hands them to the subscriber
+    pending: VecDeque<ChangeEvent>,
+    subscriber: Option<Rc<RefCell<VecDeque<ChangeEvent>>>>,
+    /// Set once the server has gone away, see disconnect
+    gone: bool,
+    /// What a blocking iterate waits on when there are no events, see watch_fd
+    watched: Vec<RawFd>,
+}
+
+impl FakeServer {
+    /// Like a real server, nothing is sent before someone subscribes
+    fn notify(&mut self, facility: ChangeFacility, kind: ChangeKind, index: u32) {
+        if self.subscriber.is_some() {
+            self.in_flight.push_back(ChangeEvent {
+                facility,
+                kind,
+                index,
+            });
+        }
+    }
+}
+
+impl FakeBackend {
+    /// A small desktop to test against:
+    ///
+    /// - sink 0 "speakers" with a speaker and a headphone port, and sink 1 "hdmi"
+    /// - source 0 "mic"
+    /// - card 0 with a stereo and an off profile
+    /// - sink input 0 "Firefox" on the speakers and source output 0 "Discord" on the mic
+    ///
+    /// Everything is at 50% and the speakers and the mic are the defaults.
+    pub fn desktop() -> FakeBackend {
+        let backend = FakeBackend::default();
+
+        let mut speakers = fake_sink(0, "speakers", "Built-in Audio Analog Stereo");
+        speakers.set_ports(
+            vec![
+                fake_port("analog-output-speaker", "Speakers"),
+                fake_port("analog-output-headphones", "Headphones"),
+            ],
+            Some("analog-output-speaker".to_string()),
+        );
+        backend.add_sink(speakers);
+        backend.add_sink(fake_sink(1, "hdmi", "HDMI / DisplayPort"));
+        backend.add_source(fake_source(0, "mic", "Built-in Audio Analog Stereo"));
+        backend.add_card(PulseCardInfo::new(
+            "alsa_card.pci".to_string(),
+            0,
+            "Built-in Audio".to_string(),
+            "module-alsa-card.c".to_string(),
+            vec![
+                fake_profile("output:analog-stereo", "Analog Stereo Output"),
+                fake_profile("off", "Off"),
+            ],
+            Some("output:analog-stereo".to_string()),
+        ));
+        backend.add_sink_input(fake_sink_input(0, "Firefox", 0));
+        backend.add_source_output(fake_source_output(0, "Discord", 0));
+
+        let mut server = backend.server.borrow_mut();
+        server.server_info.default_sink_name = "speakers".to_string();
+        server.server_info.default_source_name = "mic".to_string();
+        drop(server);
+
+        backend
+    }
+
+    pub fn add_sink(&self, sink: PulseSinkInfo) {
+        let mut server = self.server.borrow_mut();
+        server.notify(ChangeFacility::Sink, ChangeKind::New, sink.index());
+        server.sinks.push(sink);
+    }
+
+    pub fn add_source(&self, source: PulseSourceInfo) {
+        let mut server = self.server.borrow_mut();
+        server.notify(ChangeFacility::Source, ChangeKind::New, source.index());
+        server.sources.push(source);
+    }
+
+    pub fn add_sink_input(&self, input: PulseSinkInputInfo) {
+        let mut server = self.server.borrow_mut();
+        server.notify(ChangeFacility::SinkInput, ChangeKind::New, input.index());
+        server.sink_inputs.push(input);
+    }
+
+    pub fn add_source_output(&self, output: PulseSourceOutputInfo) {
+        let mut server = self.server.borrow_mut();
+        server.notify(
+            ChangeFacility::SourceOutput,
+            ChangeKind::New,
+            output.index(),
+        );
+        server.source_outputs.push(output);
+    }
+
+    pub fn add_card(&self, card: PulseCardInfo) {
+        let mut server = self.server.borrow_mut();
+        server.notify(ChangeFacility::Card, ChangeKind::New, card.index());
+        server.cards.push(card);
+    }
+
+    /// Unplugs a sink, i.e. a USB headset. Its streams are left where they are.
+    pub fn remove_sink(&self, index: u32) {
+        let mut server = self.server.borrow_mut();
+        let before = server.sinks.len();
+        server.sinks.retain(|sink| sink.index() != index);
+        if server.sinks.len() != before {
+            server.notify(ChangeFacility::Sink, ChangeKind::Removed, index);
+        }
+    }
+
+    /// Ends a stream, i.e. the music player was closed
+    pub fn remove_sink_input(&self, index: u32) {
+        let mut server = self.server.borrow_mut();
+        let before = server.sink_inputs.len();
+        server.sink_inputs.retain(|input| input.index() != index);
+        if server.sink_inputs.len() != before {
+            server.notify(ChangeFacility::SinkInput, ChangeKind::Removed, index);
+        }
+    }
+
+    /// The server goes away, i.e. it was restarted. Like a dropped connection this is only
+    /// noticed once iterate runs.
+    pub fn disconnect(&self) {
+        self.server.borrow_mut().gone = true;
+    }
+
+    pub fn default_sink_name(&self) -> String {
+        self.server.borrow().server_info.default_sink_name.clone()
+    }
+
+    pub fn default_source_name(&self) -> String {
+        self.server.borrow().server_info.default_source_name.clone()
+    }
+}
+
+impl AudioBackend for FakeBackend {
+    fn server_info(&mut self) -> Result<PulseServerInfo, RupamixError> {
+        Ok(self.server.borrow().server_info.clone())
+    }
+
+    fn sinks(&mut self) -> Result<Vec<PulseSinkInfo>, RupamixError> {
+        Ok(self.server.borrow().sinks.clone())
+    }
+
+    fn sources(&mut self) -> Result<Vec<PulseSourceInfo>, RupamixError> {
+        Ok(self.server.borrow().sources.clone())
+    }
+
+    fn sink_inputs(&mut self) -> Result<Vec<PulseSinkInputInfo>, RupamixError> {
+        Ok(self.server.borrow().sink_inputs.clone())
+    }
+
+    fn source_outputs(&mut self) -> Result<Vec<PulseSourceOutputInfo>, RupamixError> {
+        Ok(self.server.borrow().source_outputs.clone())
+    }
+
+    fn cards(&mut self) -> Result<Vec<PulseCardInfo>, RupamixError> {
+        Ok(self.server.borrow().cards.clone())
+    }
+
+    fn sink(&mut self, index: u32) -> Result<Option<PulseSinkInfo>, RupamixError> {
+        Ok(find(&self.server.borrow().sinks, index))
+    }
+
+    fn source(&mut self, index: u32) -> Result<Option<PulseSourceInfo>, RupamixError> {
+        Ok(find(&self.server.borrow().sources, index))
+    }
+
+    fn sink_input(&mut self, index: u32) -> Result<Option<PulseSinkInputInfo>, RupamixError> {
+        Ok(find(&self.server.borrow().sink_inputs, index))
+    }
+
+    fn source_output(&mut self, index: u32) -> Result<Option<PulseSourceOutputInfo>, RupamixError> {
+        Ok(find(&self.server.borrow().source_outputs, index))
+    }
+
+    fn card(&mut self, index: u32) -> Result<Option<PulseCardInfo>, RupamixError> {
+        Ok(self
+            .server
+            .borrow()
+            .cards
+            .iter()
+            .find(|card| card.index() == index)
+            .cloned())
+    }
+
+    fn set_sink_volume(&mut self, index: u32, volume: &ChannelVolumes) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if set_volume(&mut server.sinks, index, "sink", volume)? {
+            server.notify(ChangeFacility::Sink, ChangeKind::Changed, index);
+        }
+
+        Ok(())
+    }
+
+    fn set_source_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if set_volume(&mut server.sources, index, "source", volume)? {
+            server.notify(ChangeFacility::Source, ChangeKind::Changed, index);
+        }
+
+        Ok(())
+    }
+
+    fn set_sink_input_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if set_volume(&mut server.sink_inputs, index, "sink input", volume)? {
+            server.notify(ChangeFacility::SinkInput, ChangeKind::Changed, index);
+        }
+
+        Ok(())
+    }
+
+    fn set_source_output_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if set_volume(&mut server.source_outputs, index, "source output", volume)? {
+            server.notify(ChangeFacility::SourceOutput, ChangeKind::Changed, index);
+        }
+
+        Ok(())
+    }
+
+    fn set_sink_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if set_mute(&mut server.sinks, index, "sink", mute)? {
+            server.notify(ChangeFacility::Sink, ChangeKind::Changed, index);
+        }
+
+        Ok(())
+    }
+
+    fn set_source_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if set_mute(&mut server.sources, index, "source", mute)? {
+            server.notify(ChangeFacility::Source, ChangeKind::Changed, index);
+        }
+
+        Ok(())
+    }
+
+    fn set_sink_input_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if set_mute(&mut server.sink_inputs, index, "sink input", mute)? {
+            server.notify(ChangeFacility::SinkInput, ChangeKind::Changed, index);
+        }
+
+        Ok(())
+    }
+
+    fn set_source_output_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if set_mute(&mut server.source_outputs, index, "source output", mute)? {
+            server.notify(ChangeFacility::SourceOutput, ChangeKind::Changed, index);
+        }
+
+        Ok(())
+    }
+
+    fn set_default_sink(&mut self, name: &str) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if !server.sinks.iter().any(|sink| sink.name() == name) {
+            return Err(no_entity("sink", name));
+        }
+        if server.server_info.default_sink_name != name {
+            server.server_info.default_sink_name = name.to_string();
+            server.notify(ChangeFacility::Server, ChangeKind::Changed, u32::MAX);
+        }
+
+        Ok(())
+    }
+
+    fn set_default_source(&mut self, name: &str) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if !server.sources.iter().any(|source| source.name() == name) {
+            return Err(no_entity("source", name));
+        }
+        if server.server_info.default_source_name != name {
+            server.server_info.default_source_name = name.to_string();
+            server.notify(ChangeFacility::Server, ChangeKind::Changed, u32::MAX);
+        }
+
+        Ok(())
+    }
+
+    fn set_sink_port(&mut self, index: u32, port: &str) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        let sink = find_mut(&mut server.sinks, index, "sink")?;
+        let ports = with_port(sink.ports(), port)?;
+        if sink.active_port() != Some(port) {
+            sink.set_ports(ports, Some(port.to_string()));
+            server.notify(ChangeFacility::Sink, ChangeKind::Changed, index);
+        }
+
+        Ok(())
+    }
+
+    fn set_source_port(&mut self, index: u32, port: &str) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        let source = find_mut(&mut server.sources, index, "source")?;
+        let ports = with_port(source.ports(), port)?;
+        if source.active_port() != Some(port) {
+            source.set_ports(ports, Some(port.to_string()));
+            server.notify(ChangeFacility::Source, ChangeKind::Changed, index);
+        }
+
+        Ok(())
+    }
+
+    fn set_card_profile(&mut self, index: u32, profile: &str) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        let card = server
+            .cards
+            .iter_mut()
+            .find(|card| card.index() == index)
+            .ok_or_else(|| no_entity("card", &index.to_string()))?;
+        if !card.profiles().iter().any(|p| p.name == profile) {
+            return Err(no_entity("profile", profile));
+        }
+        if card.active_profile() == Some(profile) {
+            return Ok(());
+        }
+
+        *card = PulseCardInfo::new(
+            card.name().to_string(),
+            card.index(),
+            card.description().to_string(),
+            card.driver().to_string(),
+            card.profiles().to_vec(),
+            Some(profile.to_string()),
+        );
+        server.notify(ChangeFacility::Card, ChangeKind::Changed, index);
+
+        Ok(())
+    }
+
+    fn move_sink_input(&mut self, index: u32, sink: u32) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if !server.sinks.iter().any(|s| s.index() == sink) {
+            return Err(no_entity("sink", &sink.to_string()));
+        }
+        let input = find_mut(&mut server.sink_inputs, index, "sink input")?;
+        if input.sink() == sink {
+            return Ok(());
+        }
+
+        *input = PulseSinkInputInfo::new(
+            input.name().to_string(),
+            index,
+            input.application_name().to_string(),
+            input.process_binary().to_string(),
+            sink,
+            input.mute(),
+            Rc::new(RefCell::new(*input.volume().borrow())),
+            *input.channel_map(),
+        );
+        server.notify(ChangeFacility::SinkInput, ChangeKind::Changed, index);
+
+        Ok(())
+    }
+
+    fn move_source_output(&mut self, index: u32, source: u32) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if !server.sources.iter().any(|s| s.index() == source) {
+            return Err(no_entity("source", &source.to_string()));
+        }
+        let output = find_mut(&mut server.source_outputs, index, "source output")?;
+        if output.source() == source {
+            return Ok(());
+        }
+
+        *output = PulseSourceOutputInfo::new(
+            output.name().to_string(),
+            index,
+            output.application_name().to_string(),
+            output.process_binary().to_string(),
+            source,
+            output.mute(),
+            Rc::new(RefCell::new(*output.volume().borrow())),
+            *output.channel_map(),
+        );
+        server.notify(ChangeFacility::SourceOutput, ChangeKind::Changed, index);
+
+        Ok(())
+    }
+
+    fn subscribe(&mut self, queue: Rc<RefCell<VecDeque<ChangeEvent>>>) -> Result<(), RupamixError> {
+        self.server.borrow_mut().subscriber = Some(queue);
+
+        Ok(())
+    }
+
+    /// Hands over the events that have arrived, then lets the ones in flight arrive. Blocking
+    /// waits for those in flight as well.
+    ///
+    /// There is nobody else to change anything, so blocking with nothing queued only waits
+    /// a moment for the watched fds, see wait_readable. Without any it would wait forever, we end the connection instead,
+    /// which also ends Pulse::watch.
+    fn iterate(&mut self, block: bool) -> Result<(), RupamixError> {
+        let mut server = self.server.borrow_mut();
+        if server.gone {
+            return Err(RupamixError::Connection(
+                "The fake server has gone away".to_string(),
+            ));
+        }
+        if block && server.pending.is_empty() {
+            if server.in_flight.is_empty() {
+                if server.watched.is_empty() {
+                    return Err(RupamixError::Connection(
+                        "The fake server has nothing left to send".to_string(),
+                    ));
+                }
+                wait_readable();
+                return Ok(());
+            }
+            let arrived: Vec<ChangeEvent> = server.in_flight.drain(..).collect();
+            server.pending.extend(arrived);
+        }
+
+        let pending: Vec<ChangeEvent> = server.pending.drain(..).collect();
+        if let Some(queue) = &server.subscriber {
+            queue.borrow_mut().extend(pending);
+        }
+        let arrived: Vec<ChangeEvent> = server.in_flight.drain(..).collect();
+        server.pending.extend(arrived);
+
+        Ok(())
+    }
+
+    fn watch_fd(&mut self, fd: RawFd) -> Result<(), RupamixError> {
+        self.server.borrow_mut().watched.push(fd);
+
+        Ok(())
+    }
+
+    fn unwatch_fd(&mut self, fd: RawFd) {
+        self.server
+            .borrow_mut()
+            .watched
+            .retain(|watched| *watched != fd);
+    }
+}
+
+/// A stereo sink at 50%
+pub fn fake_sink(index: u32, name: &str, description: &str) -> PulseSinkInfo {
+    PulseSinkInfo::new(
+        name.to_string(),
+        index,
+        description.to_string(),
+        Rc::new(RefCell::new(half_volume())),
+        Rc::new(RefCell::new(Volume::NORMAL)),
+        false,
+        stereo_map(),
+    )
+}
+
+/// A stereo source at 50%
+pub fn fake_source(index: u32, name: &str, description: &str) -> PulseSourceInfo {
+    PulseSourceInfo::new(
+        name.to_string(),
+        index,
+        description.to_string(),
+        Rc::new(RefCell::new(half_volume())),
+        Rc::new(RefCell::new(Volume::NORMAL)),
+        false,
+        stereo_map(),
+    )
+}
+
+/// A stream playing on sink at 50%, the binary is the app in lower case
+pub fn fake_sink_input(index: u32, app: &str, sink: u32) -> PulseSinkInputInfo {
+    PulseSinkInputInfo::new(
+        "playback".to_string(),
+        index,
+        app.to_string(),
+        app.to_lowercase(),
+        sink,
+        false,
+        Rc::new(RefCell::new(half_volume())),
+        stereo_map(),
+    )
+}
+
+/// A stream recording from source at 50%, the binary is the app in lower case
+pub fn fake_source_output(index: u32, app: &str, source: u32) -> PulseSourceOutputInfo {
+    PulseSourceOutputInfo::new(
+        "recording".to_string(),
+        index,
+        app.to_string(),
+        app.to_lowercase(),
+        source,
+        false,
+        Rc::new(RefCell::new(half_volume())),
+        stereo_map(),
+    )
+}
+
+fn fake_port(name: &str, description: &str) -> PulsePortInfo {
+    PulsePortInfo {
+        name: name.to_string(),
+        description: description.to_string(),
+        priority: 0,
+        availability: PortAvailability::Unknown,
+    }
+}
+
+fn fake_profile(name: &str, description: &str) -> PulseCardProfile {
+    PulseCardProfile {
+        name: name.to_string(),
+        description: description.to_string(),
+        sinks: 1,
+        sources: 1,
+        priority: 0,
+        available: true,
+    }
+}
+
+fn half_volume() -> ChannelVolumes {
+    let mut volume = ChannelVolumes::default();
+    volume.set(2, Volume(Volume::NORMAL.0 / 2));
+    volume
+}
+
+fn stereo_map() -> Map {
+    let mut map = Map::default();
+    map.set_len(2);
+    map.get_mut()[0] = Position::FrontLeft;
+    map.get_mut()[1] = Position::FrontRight;
+    map
+}
+
+fn find<T: Device<T> + Clone>(devices: &[T], index: u32) -> Option<T> {
+    devices
+        .iter()
+        .find(|device| device.index() == index)
+        .cloned()
+}
+
+fn find_mut<'a, T: Device<T>>(
+    devices: &'a mut [T],
+    index: u32,
+    kind: &str,
+) -> Result<&'a mut T, RupamixError> {
+    devices
+        .iter_mut()
+        .find(|device| device.index() == index)
+        .ok_or_else(|| no_entity(kind, &index.to_string()))
+}
+
+/// Returns whether the volume changed, the server stays quiet if it didn't
+fn set_volume<T: Device<T>>(
+    devices: &mut [T],
+    index: u32,
+    kind: &str,
+    volume: &ChannelVolumes,
+) -> Result<bool, RupamixError> {
+    let device = find_mut(devices, index, kind)?;
+    let changed = *device.volume().borrow() != *volume;
+    *device.volume().borrow_mut() = *volume;
+
+    Ok(changed)
+}
+
+/// Returns whether the mute changed, like set_volume
+fn set_mute<T: Device<T>>(
+    devices: &mut [T],
+    index: u32,
+    kind: &str,
+    mute: bool,
+) -> Result<bool, RupamixError> {
+    let device = find_mut(devices, index, kind)?;
+    let changed = device.mute() != mute;
+    device.set_mute(mute);
+
+    Ok(changed)
+}
+
+/// The ports unchanged, as long as port is one of them
+fn with_port(ports: &[PulsePortInfo], port: &str) -> Result<Vec<PulsePortInfo>, RupamixError> {
+    if ports.iter().any(|p| p.name == port) {
+        Ok(ports.to_vec())
+    } else {
+        Err(no_entity("port", port))
+    }
+}
+
+/// Stands in for the real mainloop's poll on the watched fds. Whoever watches them reads
+/// without blocking and checks again after every iterate, so waking up early is harmless.
+fn wait_readable() {
+    thread::sleep(Duration::from_millis(10));
+}
+
+/// Asked about something the fake server doesn't have
+fn no_entity(kind: &str, name: &str) -> RupamixError {
+    RupamixError::Operation(format!("No such entity: {kind} {name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_the_server() {
+        let backend = FakeBackend::desktop();
+        let mut other = backend.clone();
+
+        other.set_default_sink("hdmi").unwrap();
+
+        assert_eq!("hdmi", backend.default_sink_name());
+        assert!(other.set_default_sink("nope").is_err());
+    }
+
+    #[test]
+    fn test_events_only_after_subscribe() {
+        let mut backend = FakeBackend::desktop();
+        let queue = Rc::new(RefCell::new(VecDeque::new()));
+
+        backend.set_sink_mute(0, true).unwrap();
+        backend.subscribe(queue.clone()).unwrap();
+        backend.remove_sink(1);
+        backend.iterate(false).unwrap();
+        assert!(queue.borrow().is_empty());
+        backend.iterate(false).unwrap();
+
+        let events: Vec<ChangeEvent> = queue.borrow().iter().copied().collect();
+        assert_eq!(
+            vec![ChangeEvent {
+                facility: ChangeFacility::Sink,
+                kind: ChangeKind::Removed,
+                index: 1,
+            }],
+            events
+        );
+        assert!(backend.iterate(true).is_err());
+    }
+
+    #[test]
+    fn test_events_only_for_actual_changes() {
+        let mut backend = FakeBackend::desktop();
+        let queue = Rc::new(RefCell::new(VecDeque::new()));
+        let volume = *backend.sink(0).unwrap().unwrap().volume().borrow();
+
+        backend.subscribe(queue.clone()).unwrap();
+        backend.set_sink_volume(0, &volume).unwrap();
+        backend.set_sink_mute(0, false).unwrap();
+        backend.set_default_sink("speakers").unwrap();
+        backend.set_sink_port(0, "analog-output-speaker").unwrap();
+        backend.set_card_profile(0, "output:analog-stereo").unwrap();
+        backend.move_sink_input(0, 0).unwrap();
+        backend.remove_sink(7);
+        assert!(backend.iterate(true).is_err());
+
+        backend.set_sink_mute(0, true).unwrap();
+        backend.iterate(true).unwrap();
+        assert_eq!(1, queue.borrow().len());
+    }
+}
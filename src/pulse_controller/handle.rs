@@ -0,0 +1,533 @@
+//! A Pulse that can be shared between threads, for applications that embed rupamix, i.e. a
+//! tray icon changing the volume from a worker thread. Pulse is built on Rc and a libpulse
+//! mainloop that belong to the thread that made them, so PulseHandle starts a thread of its own
+//! that owns the connection and runs what it is sent, one job at a time. Between jobs it keeps
+//! the state current with subscribe events, the same as the daemon.
+//!
+//! With the async feature the volume and mute calls come as async fns too. Those don't need
+//! any particular runtime, the worker wakes the task once the result is in.
+use crate::error::RupamixError;
+use crate::pulse_controller::connection::ConnectOptions;
+use crate::pulse_controller::Pulse;
+use crate::pulse_wrappers::volume_spec::VolumeSpec;
+use crate::status::Status;
+
+#[cfg(feature = "async")]
+use crate::pulse_controller::reply::{self, Reply};
+
+use std::io::{ErrorKind, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread::{self, JoinHandle};
+
+type Job = Box<dyn FnOnce(&mut Pulse) + Send>;
+
+/// Dropping the handle closes the connection and waits for the worker to finish. Share it
+/// with an Arc, every method only needs &self.
+pub struct PulseHandle {
+    jobs: Option<Sender<Job>>,
+    /// Every job is followed by a byte on here, the worker sleeps in the mainloop until
+    /// either the server or this has something for it
+    wakeup: Option<UnixStream>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl PulseHandle {
+    /// Connects on the worker thread and waits until it has, so an unreachable server is an
+    /// error here and not on the first call
+    pub fn connect(options: ConnectOptions) -> Result<PulseHandle, RupamixError> {
+        PulseHandle::spawn(move || Pulse::connect_to(&options))
+    }
+
+    /// Starts the worker on whatever Pulse connect returns, i.e. one on a FakeBackend. connect
+    /// runs on the worker thread, a Pulse can't be moved there once it is made.
+    pub fn spawn<F>(connect: F) -> Result<PulseHandle, RupamixError>
+    where
+        F: FnOnce() -> Result<Pulse, RupamixError> + Send + 'static,
+    {
+        let (ready_tx, ready_rx) = mpsc::sync_channel(1);
+        let (jobs_tx, jobs_rx) = mpsc::channel::<Job>();
+        let (wakeup, woken) = UnixStream::pair()?;
+        // A worker that is behind has wakeups enough, senders never wait for it to catch up
+        wakeup.set_nonblocking(true)?;
+        woken.set_nonblocking(true)?;
+
+        let worker = thread::Builder::new()
+            .name("rupamix-pulse".to_string())
+            .spawn(move || {
+                let pulse = connect().and_then(|mut pulse| {
+                    pulse.subscribe()?;
+                    pulse.watch_fd(woken.as_raw_fd())?;
+                    Ok(pulse)
+                });
+                match pulse {
+                    Ok(mut pulse) => {
+                        let _ = ready_tx.send(Ok(()));
+                        work(&mut pulse, jobs_rx, woken);
+                    }
+                    Err(e) => {
+                        let _ = ready_tx.send(Err(e));
+                    }
+                }
+            })?;
+
+        let handle = PulseHandle {
+            jobs: Some(jobs_tx),
+            wakeup: Some(wakeup),
+            worker: Some(worker),
+        };
+        ready_rx.recv().unwrap_or_else(|_| Err(stopped()))?;
+
+        Ok(handle)
+    }
+
+    /// Runs f with the worker's Pulse and waits for what it returns. Anything Pulse can do
+    /// can be done this way, the methods below are only the common ones.
+    pub fn run<T, F>(&self, f: F) -> Result<T, RupamixError>
+    where
+        F: FnOnce(&mut Pulse) -> Result<T, RupamixError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (reply_tx, reply_rx) = mpsc::sync_channel(1);
+
+        self.send(Box::new(move |pulse| {
+            let _ = reply_tx.send(run_job(pulse, f));
+        }));
+        reply_rx.recv().unwrap_or_else(|_| Err(stopped()))
+    }
+
+    /// If the worker is gone the job is dropped along with whatever it would have replied on,
+    /// which is how the caller finds out
+    fn send(&self, job: Job) {
+        if let Some(jobs) = &self.jobs {
+            if jobs.send(job).is_ok() {
+                self.wake_worker();
+            }
+        }
+    }
+
+    fn wake_worker(&self) {
+        if let Some(mut wakeup) = self.wakeup.as_ref() {
+            let _ = wakeup.write(&[0]);
+        }
+    }
+
+    pub fn set_sink_volume(
+        &self,
+        vol: VolumeSpec,
+        boost: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<bool, RupamixError> {
+        self.run(move |pulse| pulse.set_sink_volume(vol, boost, index, name))
+    }
+
+    pub fn increase_sink_volume(
+        &self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+        boost: bool,
+    ) -> Result<(), RupamixError> {
+        self.run(move |pulse| pulse.increase_sink_volume(inc, index, name, boost))
+    }
+
+    pub fn decrease_sink_volume(
+        &self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run(move |pulse| pulse.decrease_sink_volume(inc, index, name))
+    }
+
+    pub fn set_sink_mute(
+        &self,
+        mute: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run(move |pulse| pulse.set_sink_mute(mute, index, name))
+    }
+
+    pub fn toggle_mute(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run(move |pulse| pulse.toggle_mute(index, name))
+    }
+
+    pub fn set_source_volume(
+        &self,
+        vol: VolumeSpec,
+        boost: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<bool, RupamixError> {
+        self.run(move |pulse| pulse.set_source_volume(vol, boost, index, name))
+    }
+
+    pub fn increase_source_volume(
+        &self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+        boost: bool,
+    ) -> Result<(), RupamixError> {
+        self.run(move |pulse| pulse.increase_source_volume(inc, index, name, boost))
+    }
+
+    pub fn decrease_source_volume(
+        &self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run(move |pulse| pulse.decrease_source_volume(inc, index, name))
+    }
+
+    pub fn set_source_mute(
+        &self,
+        mute: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run(move |pulse| pulse.set_source_mute(mute, index, name))
+    }
+
+    pub fn toggle_source_mute(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run(move |pulse| pulse.toggle_source_mute(index, name))
+    }
+
+    /// What a tray icon would show for the selected sink
+    pub fn sink_status(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<Status, RupamixError> {
+        self.run(move |pulse| pulse.sink_status(index, name))
+    }
+
+    pub fn source_status(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<Status, RupamixError> {
+        self.run(move |pulse| pulse.source_status(index, name))
+    }
+}
+
+#[cfg(feature = "async")]
+impl PulseHandle {
+    /// The async run, the returned Reply resolves once the worker is done with f
+    pub fn run_async<T, F>(&self, f: F) -> Reply<T>
+    where
+        F: FnOnce(&mut Pulse) -> Result<T, RupamixError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (replier, reply) = reply::channel();
+
+        self.send(Box::new(move |pulse| replier.send(run_job(pulse, f))));
+        reply
+    }
+
+    pub async fn set_sink_volume_async(
+        &self,
+        vol: VolumeSpec,
+        boost: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<bool, RupamixError> {
+        self.run_async(move |pulse| pulse.set_sink_volume(vol, boost, index, name))
+            .await
+    }
+
+    pub async fn increase_sink_volume_async(
+        &self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+        boost: bool,
+    ) -> Result<(), RupamixError> {
+        self.run_async(move |pulse| pulse.increase_sink_volume(inc, index, name, boost))
+            .await
+    }
+
+    pub async fn decrease_sink_volume_async(
+        &self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run_async(move |pulse| pulse.decrease_sink_volume(inc, index, name))
+            .await
+    }
+
+    pub async fn set_sink_mute_async(
+        &self,
+        mute: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run_async(move |pulse| pulse.set_sink_mute(mute, index, name))
+            .await
+    }
+
+    pub async fn toggle_mute_async(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run_async(move |pulse| pulse.toggle_mute(index, name))
+            .await
+    }
+
+    pub async fn set_source_volume_async(
+        &self,
+        vol: VolumeSpec,
+        boost: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<bool, RupamixError> {
+        self.run_async(move |pulse| pulse.set_source_volume(vol, boost, index, name))
+            .await
+    }
+
+    pub async fn increase_source_volume_async(
+        &self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+        boost: bool,
+    ) -> Result<(), RupamixError> {
+        self.run_async(move |pulse| pulse.increase_source_volume(inc, index, name, boost))
+            .await
+    }
+
+    pub async fn decrease_source_volume_async(
+        &self,
+        inc: VolumeSpec,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run_async(move |pulse| pulse.decrease_source_volume(inc, index, name))
+            .await
+    }
+
+    pub async fn set_source_mute_async(
+        &self,
+        mute: bool,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run_async(move |pulse| pulse.set_source_mute(mute, index, name))
+            .await
+    }
+
+    pub async fn toggle_source_mute_async(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<(), RupamixError> {
+        self.run_async(move |pulse| pulse.toggle_source_mute(index, name))
+            .await
+    }
+
+    pub async fn sink_status_async(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<Status, RupamixError> {
+        self.run_async(move |pulse| pulse.sink_status(index, name))
+            .await
+    }
+
+    pub async fn source_status_async(
+        &self,
+        index: Option<u32>,
+        name: Option<String>,
+    ) -> Result<Status, RupamixError> {
+        self.run_async(move |pulse| pulse.source_status(index, name))
+            .await
+    }
+}
+
+impl Drop for PulseHandle {
+    fn drop(&mut self) {
+        // The worker stops once it sees the jobs channel close
+        drop(self.jobs.take());
+        self.wake_worker();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// What every call gets once the worker is gone, because the connection failed or a job
+/// panicked
+pub(crate) fn stopped() -> RupamixError {
+    RupamixError::Connection("The Pulse worker has stopped".to_string())
+}
+
+/// Runs jobs until every handle is gone or the connection is. In between it sleeps in the
+/// mainloop, which wakes up for events and for woken.
+fn work(pulse: &mut Pulse, jobs: Receiver<Job>, mut woken: UnixStream) {
+    let mut wakeups = [0; 64];
+
+    loop {
+        // Jobs are sent before their wakeup, so once the wakeups are read every job they
+        // were for is in the channel
+        loop {
+            match woken.read(&mut wakeups) {
+                Ok(0) => return,
+                Ok(_) => (),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => (),
+                Err(_) => return,
+            }
+        }
+        loop {
+            match jobs.try_recv() {
+                Ok(job) => job(pulse),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return,
+            }
+        }
+
+        if pulse.process_events(|_, _| {}).is_err() || pulse.iterate(true).is_err() {
+            return;
+        }
+    }
+}
+
+/// A job that fails is synced again afterwards, we can't know how far it got
+fn run_job<T, F>(pulse: &mut Pulse, f: F) -> Result<T, RupamixError>
+where
+    F: FnOnce(&mut Pulse) -> Result<T, RupamixError>,
+{
+    let res = f(pulse);
+    if res.is_err() {
+        let _ = pulse.update();
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pulse_controller::fake_backend::FakeBackend;
+    use std::sync::Arc;
+
+    fn fake_handle() -> PulseHandle {
+        PulseHandle::spawn(|| Pulse::with_backend(Box::new(FakeBackend::desktop()))).unwrap()
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_handle_is_send_and_sync() {
+        assert_send_sync::<PulseHandle>();
+    }
+
+    #[test]
+    fn test_changes_from_other_threads() {
+        let handle = Arc::new(fake_handle());
+
+        let workers: Vec<_> = (0..2)
+            .map(|_| {
+                let handle = handle.clone();
+                thread::spawn(move || {
+                    handle
+                        .increase_sink_volume(VolumeSpec::Percent(5.0), None, None, false)
+                        .unwrap()
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+        handle.set_source_mute(true, None, None).unwrap();
+
+        assert_eq!(60, handle.sink_status(None, None).unwrap().volume);
+        assert!(handle.source_status(None, None).unwrap().muted);
+    }
+
+    #[test]
+    fn test_errors_come_back_and_the_worker_carries_on() {
+        let handle = fake_handle();
+
+        let res =
+            handle.set_sink_volume(VolumeSpec::Percent(20.0), false, None, Some("nope".into()));
+        assert!(matches!(res, Err(RupamixError::Lookup(_))));
+
+        handle.toggle_mute(None, None).unwrap();
+        assert!(handle.sink_status(None, None).unwrap().muted);
+    }
+
+    #[test]
+    fn test_writes_that_change_nothing_keep_the_worker_going() {
+        let handle = fake_handle();
+
+        assert!(handle
+            .set_sink_volume(VolumeSpec::Percent(100.0), false, None, None)
+            .unwrap());
+        assert!(!handle
+            .set_sink_volume(VolumeSpec::Percent(100.0), false, None, None)
+            .unwrap());
+        handle
+            .increase_sink_volume(VolumeSpec::Percent(5.0), None, None, false)
+            .unwrap();
+        handle
+            .increase_sink_volume(VolumeSpec::Percent(5.0), None, None, false)
+            .unwrap();
+
+        assert_eq!(100, handle.sink_status(None, None).unwrap().volume);
+    }
+
+    #[test]
+    fn test_connect_errors_come_back() {
+        let res = PulseHandle::spawn(|| Err(RupamixError::Connection("no server".to_string())));
+
+        assert!(matches!(res, Err(RupamixError::Connection(e)) if e == "no server"));
+    }
+
+    #[test]
+    fn test_a_panicking_job_stops_the_worker() {
+        let handle = fake_handle();
+
+        let res: Result<(), RupamixError> = handle.run(|_| panic!("job panicked"));
+        assert!(matches!(res, Err(RupamixError::Connection(_))));
+        assert!(handle.sink_status(None, None).is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_async_calls() {
+        use crate::pulse_controller::reply::block_on;
+
+        let handle = fake_handle();
+
+        block_on(handle.set_sink_volume_async(VolumeSpec::Percent(20.0), false, None, None))
+            .unwrap();
+        block_on(handle.decrease_sink_volume_async(VolumeSpec::Percent(5.0), None, None)).unwrap();
+        block_on(handle.toggle_source_mute_async(None, None)).unwrap();
+
+        assert_eq!(
+            15,
+            block_on(handle.sink_status_async(None, None))
+                .unwrap()
+                .volume
+        );
+        assert!(
+            block_on(handle.source_status_async(None, None))
+                .unwrap()
+                .muted
+        );
+    }
+}
@@ -1,17 +1,46 @@
+use crate::error::RupamixError;
+use crate::pulse_controller::backend::AudioBackend;
+use crate::pulse_controller::connection::ConnectOptions;
+use crate::pulse_controller::events::ChangeEvent;
+use crate::pulse_wrappers::card_info::PulseCardInfo;
+use crate::pulse_wrappers::server_info::PulseServerInfo;
+use crate::pulse_wrappers::sink_info::PulseSinkInfo;
+use crate::pulse_wrappers::sink_input_info::PulseSinkInputInfo;
+use crate::pulse_wrappers::source_info::PulseSourceInfo;
+use crate::pulse_wrappers::source_output_info::PulseSourceOutputInfo;
+use pulse::callbacks::ListResult;
 use pulse::context::introspect::Introspector;
+use pulse::context::subscribe::InterestMaskSet;
 use pulse::context::{Context, FlagSet as ContextFlagSet, State};
 use pulse::def::Retval;
-use pulse::mainloop::standard::{IterateResult, Mainloop};
+use pulse::error::PAErr;
+use pulse::mainloop::api::{Mainloop as _, MainloopInner};
+use pulse::mainloop::events::io::{FlagSet as IoFlagSet, IoEvent};
+use pulse::mainloop::standard::{Mainloop, MainloopInternal};
 use pulse::operation::Operation;
 use pulse::proplist::Proplist;
-use std::cell::RefCell;
+use pulse::time::MicroSeconds;
+use pulse::volume::ChannelVolumes;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
 use std::ops::Deref;
+use std::os::unix::io::RawFd;
 use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What libpulse calls back with once a change went through, or didn't
+type SuccessCallback = Box<dyn FnMut(bool)>;
+
+/// An fd the mainloop polls along with the connection, see watch_fd
+type WatchedFd = IoEvent<MainloopInner<MainloopInternal>>;
 
 pub struct PulseDriver {
     pub mainloop: Rc<RefCell<Mainloop>>,
     pub context: Rc<RefCell<Context>>,
     pub introspector: Rc<RefCell<Introspector>>,
+    operation_timeout: Duration,
+    watched: Vec<(RawFd, WatchedFd)>,
 }
 
 impl Drop for PulseDriver {
@@ -21,43 +50,85 @@ impl Drop for PulseDriver {
 }
 
 impl PulseDriver {
-    pub fn connect_to_pulse() -> Result<PulseDriver, &'static str> {
+    /// Connects to the server in options, or to the one libpulse finds on its own if there is
+    /// none. If the server isn't there we try again options.retries times, waiting a little
+    /// longer each time, so a server that is restarting has a chance to come back.
+    pub fn connect_to_pulse(options: &ConnectOptions) -> Result<PulseDriver, RupamixError> {
+        let mut attempt = 0;
+
+        loop {
+            match PulseDriver::connect_once(options) {
+                Err(RupamixError::Connection(_) | RupamixError::Timeout(_))
+                    if attempt < options.retries =>
+                {
+                    thread::sleep(options.retry_delay(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// A single attempt at connecting, waits until the server is ready or options.timeout
+    /// runs out
+    fn connect_once(options: &ConnectOptions) -> Result<PulseDriver, RupamixError> {
+        let connection_err = |e: &str| RupamixError::Connection(e.to_string());
+
         let mainloop = Rc::new(RefCell::new(
-            Mainloop::new().expect("Failed to create main loop."),
+            Mainloop::new().ok_or_else(|| connection_err("Failed to create main loop."))?,
         ));
 
-        let mut proplist = Proplist::new().unwrap();
+        let mut proplist =
+            Proplist::new().ok_or_else(|| connection_err("Failed to create a proplist."))?;
         proplist
             .set_str(pulse::proplist::properties::APPLICATION_NAME, "RuPaMixa")
-            .unwrap();
+            .map_err(|_| connection_err("Failed to set the application name."))?;
 
         let context = Rc::new(RefCell::new(
             Context::new_with_proplist(mainloop.borrow().deref(), "RuPaMixaContext", &proplist)
-                .expect("Failed to create new context."),
+                .ok_or_else(|| connection_err("Failed to create new context."))?,
         ));
 
+        let server = options.server.as_ref().map(ToString::to_string);
+        let flags = if options.autospawn {
+            ContextFlagSet::NOFLAGS
+        } else {
+            ContextFlagSet::NOAUTOSPAWN
+        };
+
         context
             .borrow_mut()
-            .connect(None, ContextFlagSet::NOFLAGS, None)
-            .expect("Failed to connect to context");
+            .connect(server.as_deref(), flags, None)
+            .map_err(|e| {
+                RupamixError::Connection(format!(
+                    "Failed to connect to {}: {e}",
+                    options.describe_server()
+                ))
+            })?;
 
         // wait for context to be ready
+        let deadline = Instant::now() + options.timeout;
         loop {
-            match mainloop.borrow_mut().iterate(false) {
-                IterateResult::Quit(_) | IterateResult::Err(_) => {
-                    return Err("Iterate state was not success, quitting...");
-                }
-                IterateResult::Success(_) => {}
-            }
             match context.borrow().get_state() {
                 State::Ready => {
                     break;
                 }
                 State::Failed | State::Terminated => {
-                    return Err("Context in state failed/teminated");
+                    return Err(RupamixError::Connection(format!(
+                        "Could not connect to {}: {}",
+                        options.describe_server(),
+                        context.borrow().errno()
+                    )));
                 }
                 _ => {}
             }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(options.timeout_error());
+            }
+            iterate_mainloop(&mainloop, Some(remaining))
+                .map_err(|e| RupamixError::Connection(format!("Error in mainloop: {e}")))?;
         }
 
         let introspector = Rc::new(RefCell::new(context.borrow().introspect()));
@@ -66,32 +137,487 @@ impl PulseDriver {
             mainloop,
             context,
             introspector,
+            operation_timeout: options.operation_timeout,
+            watched: Vec::new(),
         })
     }
 
-    pub fn wait_for_op<T: ?Sized>(&mut self, op: Operation<T>) -> Result<(), &'static str> {
+    /// Sleeps in the mainloop until op is done, or gives up on it once the operation
+    /// timeout runs out
+    pub fn wait_for_op<T: ?Sized>(&mut self, mut op: Operation<T>) -> Result<(), RupamixError> {
+        let deadline = Instant::now() + self.operation_timeout;
+
         loop {
-            // This match must be there
-            match self.mainloop.borrow_mut().iterate(false) {
-                IterateResult::Quit(_) => return Err("Mainloop quit..."),
-                IterateResult::Err(_) => {
-                    return Err("Error in mainloop");
-                }
-                IterateResult::Success(_) => (),
-            }
             match op.get_state() {
                 pulse::operation::State::Running => (),
-                pulse::operation::State::Cancelled => {
-                    return Err("Operation was calceled");
-                }
+                pulse::operation::State::Cancelled => return Err(self.cancelled_error()),
                 pulse::operation::State::Done => break,
             }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                // Otherwise the callback could still run after we have moved on
+                op.cancel();
+                return Err(RupamixError::Timeout(format!(
+                    "The server didn't answer within {:?}",
+                    self.operation_timeout
+                )));
+            }
+            self.iterate_for(Some(remaining))?;
         }
         Ok(())
     }
 
+    /// wait_for_op for the changes. The server only says whether a change worked, why it
+    /// didn't is left in the context's errno.
+    fn wait_for_change(
+        &mut self,
+        op: Operation<dyn FnMut(bool)>,
+        success: &Cell<bool>,
+        what: &str,
+    ) -> Result<(), RupamixError> {
+        self.wait_for_op(op)?;
+
+        if success.get() {
+            Ok(())
+        } else {
+            Err(RupamixError::Operation(format!(
+                "{what} failed: {}",
+                self.context.borrow().errno()
+            )))
+        }
+    }
+
+    /// Runs the mainloop once, waiting at most timeout for something to happen.
+    /// None waits for as long as it takes.
+    fn iterate_for(&mut self, timeout: Option<Duration>) -> Result<(), RupamixError> {
+        iterate_mainloop(&self.mainloop, timeout)
+            .map_err(|e| RupamixError::Operation(format!("Error in mainloop: {e}")))
+    }
+
+    /// Pulse cancels everything that is still running when the connection goes away
+    fn cancelled_error(&self) -> RupamixError {
+        match self.context.borrow().get_state() {
+            State::Failed | State::Terminated => RupamixError::Connection(format!(
+                "Lost the connection to the server: {}",
+                self.context.borrow().errno()
+            )),
+            _ => RupamixError::Operation("Operation was cancelled".to_string()),
+        }
+    }
+
     fn shutdown(&mut self) {
         self.mainloop.borrow_mut().quit(Retval(0));
         self.context.borrow_mut().disconnect();
     }
 }
+
+/// Listings are collected into a Vec as the callbacks come in, and handed back once the
+/// operation is done. Changes wait for the server to get them before returning.
+impl AudioBackend for PulseDriver {
+    fn server_info(&mut self) -> Result<PulseServerInfo, RupamixError> {
+        let server_info = Rc::new(RefCell::new(PulseServerInfo::default()));
+        let result = server_info.clone();
+
+        let op = self
+            .introspector
+            .borrow()
+            .get_server_info(move |info| result.borrow_mut().update(info));
+
+        self.wait_for_op(op)?;
+        Ok(server_info.take())
+    }
+
+    fn sinks(&mut self) -> Result<Vec<PulseSinkInfo>, RupamixError> {
+        let sinks = Rc::new(RefCell::new(Vec::new()));
+        let list = sinks.clone();
+
+        let op = self
+            .introspector
+            .borrow()
+            .get_sink_info_list(move |result| {
+                if let ListResult::Item(info) = result {
+                    list.borrow_mut().push(PulseSinkInfo::from(info));
+                }
+            });
+
+        self.wait_for_op(op)?;
+        Ok(sinks.take())
+    }
+
+    fn sources(&mut self) -> Result<Vec<PulseSourceInfo>, RupamixError> {
+        let sources = Rc::new(RefCell::new(Vec::new()));
+        let list = sources.clone();
+
+        let op = self
+            .introspector
+            .borrow()
+            .get_source_info_list(move |result| {
+                if let ListResult::Item(info) = result {
+                    list.borrow_mut().push(PulseSourceInfo::from(info));
+                }
+            });
+
+        self.wait_for_op(op)?;
+        Ok(sources.take())
+    }
+
+    fn sink_inputs(&mut self) -> Result<Vec<PulseSinkInputInfo>, RupamixError> {
+        let inputs = Rc::new(RefCell::new(Vec::new()));
+        let list = inputs.clone();
+
+        let op = self
+            .introspector
+            .borrow()
+            .get_sink_input_info_list(move |result| {
+                if let ListResult::Item(info) = result {
+                    list.borrow_mut().push(PulseSinkInputInfo::from(info));
+                }
+            });
+
+        self.wait_for_op(op)?;
+        Ok(inputs.take())
+    }
+
+    fn source_outputs(&mut self) -> Result<Vec<PulseSourceOutputInfo>, RupamixError> {
+        let outputs = Rc::new(RefCell::new(Vec::new()));
+        let list = outputs.clone();
+
+        let op = self
+            .introspector
+            .borrow()
+            .get_source_output_info_list(move |result| {
+                if let ListResult::Item(info) = result {
+                    list.borrow_mut().push(PulseSourceOutputInfo::from(info));
+                }
+            });
+
+        self.wait_for_op(op)?;
+        Ok(outputs.take())
+    }
+
+    fn cards(&mut self) -> Result<Vec<PulseCardInfo>, RupamixError> {
+        let cards = Rc::new(RefCell::new(Vec::new()));
+        let list = cards.clone();
+
+        let op = self
+            .introspector
+            .borrow()
+            .get_card_info_list(move |result| {
+                if let ListResult::Item(info) = result {
+                    list.borrow_mut().push(PulseCardInfo::from(info));
+                }
+            });
+
+        self.wait_for_op(op)?;
+        Ok(cards.take())
+    }
+
+    fn sink(&mut self, index: u32) -> Result<Option<PulseSinkInfo>, RupamixError> {
+        let sink = Rc::new(RefCell::new(None));
+        let found = sink.clone();
+
+        let op = self
+            .introspector
+            .borrow()
+            .get_sink_info_by_index(index, move |result| {
+                if let ListResult::Item(info) = result {
+                    *found.borrow_mut() = Some(PulseSinkInfo::from(info));
+                }
+            });
+
+        self.wait_for_op(op)?;
+        Ok(sink.take())
+    }
+
+    fn source(&mut self, index: u32) -> Result<Option<PulseSourceInfo>, RupamixError> {
+        let source = Rc::new(RefCell::new(None));
+        let found = source.clone();
+
+        let op = self
+            .introspector
+            .borrow()
+            .get_source_info_by_index(index, move |result| {
+                if let ListResult::Item(info) = result {
+                    *found.borrow_mut() = Some(PulseSourceInfo::from(info));
+                }
+            });
+
+        self.wait_for_op(op)?;
+        Ok(source.take())
+    }
+
+    fn sink_input(&mut self, index: u32) -> Result<Option<PulseSinkInputInfo>, RupamixError> {
+        let input = Rc::new(RefCell::new(None));
+        let found = input.clone();
+
+        let op = self
+            .introspector
+            .borrow()
+            .get_sink_input_info(index, move |result| {
+                if let ListResult::Item(info) = result {
+                    *found.borrow_mut() = Some(PulseSinkInputInfo::from(info));
+                }
+            });
+
+        self.wait_for_op(op)?;
+        Ok(input.take())
+    }
+
+    fn source_output(&mut self, index: u32) -> Result<Option<PulseSourceOutputInfo>, RupamixError> {
+        let output = Rc::new(RefCell::new(None));
+        let found = output.clone();
+
+        let op = self
+            .introspector
+            .borrow()
+            .get_source_output_info(index, move |result| {
+                if let ListResult::Item(info) = result {
+                    *found.borrow_mut() = Some(PulseSourceOutputInfo::from(info));
+                }
+            });
+
+        self.wait_for_op(op)?;
+        Ok(output.take())
+    }
+
+    fn card(&mut self, index: u32) -> Result<Option<PulseCardInfo>, RupamixError> {
+        let card = Rc::new(RefCell::new(None));
+        let found = card.clone();
+
+        let op = self
+            .introspector
+            .borrow()
+            .get_card_info_by_index(index, move |result| {
+                if let ListResult::Item(info) = result {
+                    *found.borrow_mut() = Some(PulseCardInfo::from(info));
+                }
+            });
+
+        self.wait_for_op(op)?;
+        Ok(card.take())
+    }
+
+    fn set_sink_volume(&mut self, index: u32, volume: &ChannelVolumes) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op =
+            self.introspector
+                .borrow_mut()
+                .set_sink_volume_by_index(index, volume, Some(callback));
+
+        self.wait_for_change(op, &success, "Setting the sink volume")
+    }
+
+    fn set_source_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op = self.introspector.borrow_mut().set_source_volume_by_index(
+            index,
+            volume,
+            Some(callback),
+        );
+
+        self.wait_for_change(op, &success, "Setting the source volume")
+    }
+
+    fn set_sink_input_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op =
+            self.introspector
+                .borrow_mut()
+                .set_sink_input_volume(index, volume, Some(callback));
+
+        self.wait_for_change(op, &success, "Setting the stream volume")
+    }
+
+    fn set_source_output_volume(
+        &mut self,
+        index: u32,
+        volume: &ChannelVolumes,
+    ) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op =
+            self.introspector
+                .borrow_mut()
+                .set_source_output_volume(index, volume, Some(callback));
+
+        self.wait_for_change(op, &success, "Setting the recording stream volume")
+    }
+
+    fn set_sink_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op = self
+            .introspector
+            .borrow_mut()
+            .set_sink_mute_by_index(index, mute, Some(callback));
+
+        self.wait_for_change(op, &success, "Muting the sink")
+    }
+
+    fn set_source_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op =
+            self.introspector
+                .borrow_mut()
+                .set_source_mute_by_index(index, mute, Some(callback));
+
+        self.wait_for_change(op, &success, "Muting the source")
+    }
+
+    fn set_sink_input_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op = self
+            .introspector
+            .borrow_mut()
+            .set_sink_input_mute(index, mute, Some(callback));
+
+        self.wait_for_change(op, &success, "Muting the stream")
+    }
+
+    fn set_source_output_mute(&mut self, index: u32, mute: bool) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op = self
+            .introspector
+            .borrow_mut()
+            .set_source_output_mute(index, mute, Some(callback));
+
+        self.wait_for_change(op, &success, "Muting the recording stream")
+    }
+
+    fn set_default_sink(&mut self, name: &str) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op = self.context.borrow_mut().set_default_sink(name, callback);
+
+        self.wait_for_change(op, &success, "Setting the default sink")
+    }
+
+    fn set_default_source(&mut self, name: &str) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op = self.context.borrow_mut().set_default_source(name, callback);
+
+        self.wait_for_change(op, &success, "Setting the default source")
+    }
+
+    fn set_sink_port(&mut self, index: u32, port: &str) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op = self
+            .introspector
+            .borrow_mut()
+            .set_sink_port_by_index(index, port, Some(callback));
+
+        self.wait_for_change(op, &success, "Switching the sink port")
+    }
+
+    fn set_source_port(&mut self, index: u32, port: &str) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op =
+            self.introspector
+                .borrow_mut()
+                .set_source_port_by_index(index, port, Some(callback));
+
+        self.wait_for_change(op, &success, "Switching the source port")
+    }
+
+    fn set_card_profile(&mut self, index: u32, profile: &str) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op = self.introspector.borrow_mut().set_card_profile_by_index(
+            index,
+            profile,
+            Some(callback),
+        );
+
+        self.wait_for_change(op, &success, "Switching the card profile")
+    }
+
+    fn move_sink_input(&mut self, index: u32, sink: u32) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op =
+            self.introspector
+                .borrow_mut()
+                .move_sink_input_by_index(index, sink, Some(callback));
+
+        self.wait_for_change(op, &success, "Moving the stream")
+    }
+
+    fn move_source_output(&mut self, index: u32, source: u32) -> Result<(), RupamixError> {
+        let (success, callback) = success_callback();
+        let op = self.introspector.borrow_mut().move_source_output_by_index(
+            index,
+            source,
+            Some(callback),
+        );
+
+        self.wait_for_change(op, &success, "Moving the recording stream")
+    }
+
+    fn subscribe(&mut self, queue: Rc<RefCell<VecDeque<ChangeEvent>>>) -> Result<(), RupamixError> {
+        self.context
+            .borrow_mut()
+            .set_subscribe_callback(Some(Box::new(move |facility, operation, index| {
+                if let Some(event) = ChangeEvent::from_pulse(facility, operation, index) {
+                    queue.borrow_mut().push_back(event);
+                }
+            })));
+
+        let mask = InterestMaskSet::SINK
+            | InterestMaskSet::SOURCE
+            | InterestMaskSet::SINK_INPUT
+            | InterestMaskSet::SOURCE_OUTPUT
+            | InterestMaskSet::CARD
+            | InterestMaskSet::SERVER;
+        let op = self.context.borrow_mut().subscribe(mask, |_| {});
+        self.wait_for_op(op)
+    }
+
+    fn iterate(&mut self, block: bool) -> Result<(), RupamixError> {
+        self.iterate_for(if block { None } else { Some(Duration::ZERO) })
+    }
+
+    /// The fd is only there to wake poll up, so there is nothing to do once it has
+    fn watch_fd(&mut self, fd: RawFd) -> Result<(), RupamixError> {
+        let watched = self
+            .mainloop
+            .borrow_mut()
+            .new_io_event(fd, IoFlagSet::INPUT, Box::new(|_, _, _| ()))
+            .ok_or_else(|| RupamixError::Operation(format!("Unable to watch fd {fd}")))?;
+        self.watched.push((fd, watched));
+
+        Ok(())
+    }
+
+    /// Dropping the event takes it out of the mainloop
+    fn unwatch_fd(&mut self, fd: RawFd) {
+        self.watched.retain(|(watched, _)| *watched != fd);
+    }
+}
+
+/// The callback the changes hand to libpulse, along with where it leaves whether the change
+/// worked. Nothing is a failure, in case the callback never runs.
+fn success_callback() -> (Rc<Cell<bool>>, SuccessCallback) {
+    let success = Rc::new(Cell::new(false));
+    let result = success.clone();
+
+    (success, Box::new(move |ok| result.set(ok)))
+}
+
+/// One round of prepare, poll and dispatch, which is what Mainloop::iterate does, except that
+/// the poll gives up after timeout instead of blocking forever or not at all
+fn iterate_mainloop(mainloop: &RefCell<Mainloop>, timeout: Option<Duration>) -> Result<(), PAErr> {
+    // prepare takes an i32 worth of microseconds, about 35 minutes
+    let timeout =
+        timeout.map(|timeout| MicroSeconds(timeout.as_micros().min(i32::MAX as u128) as u64));
+    let mut mainloop = mainloop.borrow_mut();
+
+    mainloop.prepare(timeout)?;
+    mainloop.poll()?;
+    mainloop.dispatch()?;
+
+    Ok(())
+}
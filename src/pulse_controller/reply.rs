@@ -0,0 +1,134 @@
+//! The future PulseHandle's async calls wait on. It is a one shot channel: the worker thread
+//! puts the result in and wakes whoever polled last, and if the worker goes away without
+//! answering the future resolves to an error instead of hanging.
+use crate::error::RupamixError;
+use crate::pulse_controller::handle::stopped;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+
+struct Shared<T> {
+    result: Option<Result<T, RupamixError>>,
+    waker: Option<Waker>,
+    /// Set once the Replier is gone, with or without a result
+    closed: bool,
+}
+
+pub struct Reply<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+/// The worker's end
+pub(crate) struct Replier<T> {
+    shared: Arc<Mutex<Shared<T>>>,
+}
+
+pub(crate) fn channel<T>() -> (Replier<T>, Reply<T>) {
+    let shared = Arc::new(Mutex::new(Shared {
+        result: None,
+        waker: None,
+        closed: false,
+    }));
+
+    (
+        Replier {
+            shared: shared.clone(),
+        },
+        Reply { shared },
+    )
+}
+
+/// Nothing is ever left half written in Shared, so a panic while it was locked changes nothing
+fn lock<T>(shared: &Mutex<Shared<T>>) -> MutexGuard<'_, Shared<T>> {
+    shared.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<T> Replier<T> {
+    /// The waking happens when self is dropped at the end of this
+    pub fn send(self, result: Result<T, RupamixError>) {
+        lock(&self.shared).result = Some(result);
+    }
+}
+
+impl<T> Drop for Replier<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut shared = lock(&self.shared);
+            shared.closed = true;
+            shared.waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Future for Reply<T> {
+    type Output = Result<T, RupamixError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = lock(&self.shared);
+
+        if let Some(result) = shared.result.take() {
+            Poll::Ready(result)
+        } else if shared.closed {
+            Poll::Ready(Err(stopped()))
+        } else {
+            shared.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// Just enough of an executor for the tests
+#[cfg(test)]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    use std::task::Wake;
+    use std::thread::{self, Thread};
+
+    struct Unpark(Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_reply_from_another_thread() {
+        let (replier, reply) = channel();
+
+        let worker = thread::spawn(move || replier.send(Ok(42)));
+
+        assert_eq!(42, block_on(reply).unwrap());
+        worker.join().unwrap();
+    }
+
+    #[test]
+    fn test_dropped_replier_is_an_error() {
+        let (replier, reply) = channel::<()>();
+
+        drop(replier);
+
+        assert!(matches!(block_on(reply), Err(RupamixError::Connection(_))));
+    }
+}
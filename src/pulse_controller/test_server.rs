@@ -0,0 +1,129 @@
+//! A throwaway PulseAudio server for the tests that need a real one. Each TestServer gets its
+//! own runtime directory and socket, so tests can run in parallel and never touch the user's
+//! own server or speakers.
+//!
+//! The server has two null sinks and a null source, all starting at 50%:
+//!
+//! - rupamix_test_sink (the default) and rupamix_test_sink2
+//! - rupamix_test_source (the default)
+//!
+//! This needs the pulseaudio binary, pipewire-pulse can't be started on its own like this.
+use crate::pulse_controller::connection::{ConnectOptions, ServerAddress};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub static TEST_SINK: &str = "rupamix_test_sink";
+pub static TEST_SINK2: &str = "rupamix_test_sink2";
+pub static TEST_SOURCE: &str = "rupamix_test_source";
+
+/// 50% of Volume::NORMAL, what everything starts at
+static START_VOLUME: u32 = 0x8000;
+static STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+pub struct TestServer {
+    child: Child,
+    dir: PathBuf,
+    socket: PathBuf,
+}
+
+impl TestServer {
+    /// Starts the server and waits until its socket is there. Panics if it can't, a test
+    /// that wanted a server can't do anything without one.
+    pub fn start() -> TestServer {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("rupamix-test-{}-{id}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("Failed to create the test server directory");
+
+        let socket = dir.join("native");
+        let script = dir.join("default.pa");
+        fs::write(&script, startup_script(&socket))
+            .expect("Failed to write the test server script");
+
+        let child = Command::new("pulseaudio")
+            .args([
+                "--daemonize=no",
+                "--system=no",
+                "--exit-idle-time=-1",
+                "--use-pid-file=no",
+                "--disable-shm=yes",
+                "--realtime=no",
+                "--high-priority=no",
+                "--log-target=stderr",
+                "-n",
+                "-F",
+            ])
+            .arg(&script)
+            // Keep it away from the user's config, state and session
+            .env("HOME", &dir)
+            .env("XDG_RUNTIME_DIR", &dir)
+            .env("XDG_CONFIG_HOME", &dir)
+            .env("PULSE_RUNTIME_PATH", &dir)
+            .env("PULSE_STATE_PATH", &dir)
+            .env_remove("PULSE_SERVER")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Failed to start pulseaudio, is it installed?");
+
+        let mut server = TestServer { child, dir, socket };
+        server.wait_for_socket();
+        server
+    }
+
+    /// What to give Pulse::connect_to. There is never a reason to start another server here.
+    pub fn connect_options(&self) -> ConnectOptions {
+        ConnectOptions {
+            autospawn: false,
+            ..ConnectOptions::to_server(ServerAddress::Unix(self.socket.clone()))
+        }
+    }
+
+    fn wait_for_socket(&mut self) {
+        let start = Instant::now();
+
+        while !self.socket.exists() {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                panic!("pulseaudio exited before it was ready: {status}");
+            }
+            if start.elapsed() > STARTUP_TIMEOUT {
+                panic!("pulseaudio didn't create {} in time", self.socket.display());
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Only the modules we need, anything that would remember volumes between runs is left out
+fn startup_script(socket: &Path) -> String {
+    format!(
+        "load-module module-native-protocol-unix socket={socket} auth-anonymous=1\n\
+         load-module module-null-sink sink_name={TEST_SINK} \
+         sink_properties=device.description=Rupamix_Test_Sink\n\
+         load-module module-null-sink sink_name={TEST_SINK2} \
+         sink_properties=device.description=Rupamix_Test_Sink_2\n\
+         load-module module-null-source source_name={TEST_SOURCE} \
+         description=Rupamix_Test_Source\n\
+         set-sink-volume {TEST_SINK} {START_VOLUME}\n\
+         set-sink-volume {TEST_SINK2} {START_VOLUME}\n\
+         set-source-volume {TEST_SOURCE} {START_VOLUME}\n\
+         set-default-sink {TEST_SINK}\n\
+         set-default-source {TEST_SOURCE}\n",
+        socket = socket.display()
+    )
+}
@@ -1,4 +1,10 @@
+pub mod card_info;
 pub mod device;
+pub mod device_metadata;
+pub mod port_info;
 pub mod server_info;
 pub mod sink_info;
+pub mod sink_input_info;
 pub mod source_info;
+pub mod source_output_info;
+pub mod volume_spec;
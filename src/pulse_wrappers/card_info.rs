@@ -0,0 +1,159 @@
+//! A wrapper around CardInfo. A card is the hardware behind sinks and sources, and its profile
+//! decides which of them exist, i.e. A2DP or HSP/HFP on a Bluetooth headset.
+use crate::pulse_controller::device_manager::DeviceError;
+use crate::pulse_controller::device_pattern::find_named;
+use pulse::context::introspect::{CardInfo, CardProfileInfo};
+use pulse::proplist::properties;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PulseCardProfile {
+    pub name: String,
+    pub description: String,
+    pub sinks: u32,
+    pub sources: u32,
+    pub priority: u32,
+    pub available: bool,
+}
+
+impl From<&'_ CardProfileInfo<'_>> for PulseCardProfile {
+    fn from(item: &CardProfileInfo) -> Self {
+        PulseCardProfile {
+            name: item.name.clone().map(String::from).unwrap_or_default(),
+            description: item
+                .description
+                .clone()
+                .map(String::from)
+                .unwrap_or_default(),
+            sinks: item.n_sinks,
+            sources: item.n_sources,
+            priority: item.priority,
+            available: item.available,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct PulseCardInfo {
+    name: String,
+    index: u32,
+    description: String,
+    driver: String,
+    profiles: Vec<PulseCardProfile>,
+    active_profile: Option<String>,
+}
+
+impl PulseCardInfo {
+    pub fn new(
+        name: String,
+        index: u32,
+        description: String,
+        driver: String,
+        profiles: Vec<PulseCardProfile>,
+        active_profile: Option<String>,
+    ) -> PulseCardInfo {
+        PulseCardInfo {
+            name,
+            index,
+            description,
+            driver,
+            profiles,
+            active_profile,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    pub fn driver(&self) -> &str {
+        &self.driver
+    }
+
+    pub fn profiles(&self) -> &[PulseCardProfile] {
+        &self.profiles
+    }
+
+    /// The name of the profile in use, None if the server didn't tell us
+    pub fn active_profile(&self) -> Option<&str> {
+        self.active_profile.as_deref()
+    }
+
+    /// Finds a profile by name, or by a pattern the same way devices are found
+    pub fn find_profile(&self, pattern: &str) -> Result<&PulseCardProfile, DeviceError> {
+        find_named(&self.profiles, pattern, "profile", |profile| {
+            (profile.name.clone(), profile.description.clone())
+        })
+    }
+}
+
+impl From<&'_ CardInfo<'_>> for PulseCardInfo {
+    fn from(item: &CardInfo) -> Self {
+        PulseCardInfo {
+            name: item.name.clone().map(String::from).unwrap_or_default(),
+            index: item.index,
+            description: item
+                .proplist
+                .get_str(properties::DEVICE_DESCRIPTION)
+                .unwrap_or_default(),
+            driver: item.driver.clone().map(String::from).unwrap_or_default(),
+            profiles: item.profiles.iter().map(PulseCardProfile::from).collect(),
+            active_profile: item
+                .active_profile
+                .as_ref()
+                .and_then(|profile| profile.name.clone())
+                .map(String::from),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str, description: &str) -> PulseCardProfile {
+        PulseCardProfile {
+            name: name.to_string(),
+            description: description.to_string(),
+            sinks: 1,
+            sources: 1,
+            priority: 0,
+            available: true,
+        }
+    }
+
+    fn headset() -> PulseCardInfo {
+        PulseCardInfo::new(
+            "bluez_card.00_1B_66_A1_2C_3D".to_string(),
+            1,
+            "WH-1000XM4".to_string(),
+            "module-bluez5-device.c".to_string(),
+            vec![
+                profile("a2dp-sink", "High Fidelity Playback (A2DP Sink)"),
+                profile("headset-head-unit", "Headset Head Unit (HSP/HFP)"),
+                profile("off", "Off"),
+            ],
+            Some("a2dp-sink".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_find_profile() {
+        let card = headset();
+
+        assert_eq!("off", card.find_profile("off").unwrap().name);
+        assert_eq!("headset-head-unit", card.find_profile("hfp").unwrap().name);
+        assert!(matches!(
+            card.find_profile("h"),
+            Err(DeviceError::Ambiguous(_))
+        ));
+    }
+}
@@ -1,14 +1,74 @@
-use pulse::volume::{ChannelVolumes, Volume, VolumeDB};
+use crate::pulse_controller::device_manager::DeviceError;
+use crate::pulse_controller::device_pattern::find_named;
+use crate::pulse_wrappers::device_metadata::DeviceMetadata;
+use crate::pulse_wrappers::port_info::{PortAvailability, PulsePortInfo};
+use crate::pulse_wrappers::volume_spec::{VolumeSpec, VolumeUnit};
+use pulse::channelmap::{Map, Position};
+use pulse::volume::{ChannelVolumes, Volume};
 use std::cell::RefCell;
-use std::cmp::Ordering;
-use std::fs;
-use std::io::{Error, ErrorKind, Read, Write};
 use std::rc::Rc;
 
 pub static MAX_VOLUME: u8 = 100;
 pub static MAX_VOLUME_BOOSTED: u8 = 120;
-static FILE: &str = "/tmp/rupamix_vol";
-pub static APPROX_ONE_PCT: VolumeDB = VolumeDB(-120.0);
+
+/// How loud a device is allowed to get, in percent, with and without boost. When
+/// relative_to_base is set the percentages are of the device's base volume instead of
+/// Volume::NORMAL, so that boost means the same amount of amplification on every card.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VolumeLimits {
+    max: f64,
+    max_boosted: f64,
+    relative_to_base: bool,
+}
+
+impl Default for VolumeLimits {
+    fn default() -> Self {
+        VolumeLimits {
+            max: f64::from(MAX_VOLUME),
+            max_boosted: f64::from(MAX_VOLUME_BOOSTED),
+            relative_to_base: false,
+        }
+    }
+}
+
+impl VolumeLimits {
+    /// Negative limits are treated as 0 and the boosted limit is never below the normal one
+    pub fn new(max: f64, max_boosted: f64, relative_to_base: bool) -> VolumeLimits {
+        let max = max.max(0.0);
+        VolumeLimits {
+            max,
+            max_boosted: max_boosted.max(max),
+            relative_to_base,
+        }
+    }
+
+    pub fn max(&self) -> f64 {
+        self.max
+    }
+
+    pub fn max_boosted(&self) -> f64 {
+        self.max_boosted
+    }
+
+    pub fn relative_to_base(&self) -> bool {
+        self.relative_to_base
+    }
+
+    /// The highest volume we allow, never past Pulse's own Volume::MAX. A base volume that
+    /// isn't valid or is muted can't be scaled from, so we fall back to Volume::NORMAL.
+    pub fn ceiling(&self, boost: bool, base_volume: Volume) -> Volume {
+        let pct = if boost { self.max_boosted } else { self.max };
+        let reference =
+            if self.relative_to_base && base_volume.is_valid() && !base_volume.is_muted() {
+                base_volume
+            } else {
+                Volume::NORMAL
+            };
+        let raw = (f64::from(reference.0) * pct / 100.0).round();
+
+        Volume(raw.min(f64::from(Volume::MAX.0)) as u32)
+    }
+}
 
 pub trait Device<T> {
     fn index(&self) -> u32;
@@ -16,109 +76,348 @@ pub trait Device<T> {
     fn volume(&self) -> Rc<RefCell<ChannelVolumes>>;
     fn base_volume(&self) -> Rc<RefCell<Volume>>;
     fn description(&self) -> &str;
+    fn mute(&self) -> bool;
+    fn set_mute(&mut self, mute: bool);
+    fn channel_map(&self) -> &Map;
+    fn limits(&self) -> VolumeLimits;
+    fn set_limits(&mut self, limits: VolumeLimits);
+
+    /// Sinks and sources have ports, streams don't
+    fn ports(&self) -> &[PulsePortInfo] {
+        &[]
+    }
 
-    fn increase_volume(&mut self, inc: &u8, boost: bool) {
-        let initial = self.get_volume_as_pct();
-        let mut current = initial;
-        // We don't saturate here because we only want a number as big as MAX_VOLUME_BOOSTED
-        let mut new_vol = initial.checked_add(*inc).unwrap_or(MAX_VOLUME_BOOSTED);
+    /// The name of the port in use, if there are any
+    fn active_port(&self) -> Option<&str> {
+        None
+    }
 
-        if boost {
-            if new_vol > MAX_VOLUME_BOOSTED {
-                new_vol = MAX_VOLUME_BOOSTED;
-            }
-        } else if new_vol > MAX_VOLUME {
-            new_vol = MAX_VOLUME;
+    /// State, driver, sample spec and the rest, see DeviceMetadata. Streams have none.
+    fn metadata(&self) -> Option<&DeviceMetadata> {
+        None
+    }
+
+    /// Finds a port by name, or by a pattern the same way devices are found
+    fn find_port(&self, pattern: &str) -> Result<&PulsePortInfo, DeviceError> {
+        if self.ports().is_empty() {
+            return Err(DeviceError::NameNotFound(format!(
+                "{} has no ports",
+                self.name()
+            )));
         }
 
-        while current < new_vol {
-            self.volume()
-                .borrow_mut()
-                .increase(Volume::from(APPROX_ONE_PCT));
-            current = self.get_volume_as_pct();
+        find_named(self.ports(), pattern, "port", |port| {
+            (port.name.clone(), port.description.clone())
+        })
+    }
+
+    /// The highest volume this device may be set to, see VolumeLimits
+    fn volume_ceiling(&self, boost: bool) -> Volume {
+        let base_volume = *self.base_volume().borrow();
+        self.limits().ceiling(boost, base_volume)
+    }
+
+    /// Increases the volume by inc. The new volume is worked out up front, and for percent and
+    /// raw steps an increase followed by the same decrease lands on the exact same raw value.
+    fn increase_volume(&mut self, inc: VolumeSpec, boost: bool) {
+        let current = max_volume(&self.volume().borrow());
+        let new_vol = inc.raise(current).min(self.volume_ceiling(boost));
+
+        // Never let an increase pull a boosted volume back down to the ceiling
+        if new_vol > current {
+            self.set_volume_to(new_vol);
         }
     }
 
-    fn decrease_volume(&mut self, inc: &u8) {
-        let initial = self.get_volume_as_pct();
-        let mut current = initial;
+    fn decrease_volume(&mut self, inc: VolumeSpec) {
+        let current = max_volume(&self.volume().borrow());
         // Stop at the bounds
-        let new_vol = initial.saturating_sub(*inc);
-
-        // We already know that the smallest the number can get to is zero, no checks needed.
-        while current > new_vol {
-            self.volume()
-                .borrow_mut()
-                .decrease(Volume::from(APPROX_ONE_PCT));
-            current = self.get_volume_as_pct();
+        let new_vol = inc.lower(current);
+
+        self.set_volume_to(new_vol);
+    }
+
+    /// Sets the volume to vol, capped at the ceiling. Returns false if it already was, there
+    /// is nothing to change then.
+    fn set_volume(&mut self, vol: VolumeSpec, boost: bool) -> bool {
+        let current = max_volume(&self.volume().borrow());
+        let new_vol = vol.to_volume().min(self.volume_ceiling(boost));
+
+        if new_vol == current {
+            false
+        } else {
+            self.set_volume_to(new_vol);
+            true
         }
     }
 
-    #[allow(clippy::comparison_chain)]
-    fn set_volume(&mut self, vol: u8, boost: bool) {
-        let current_vol = self.get_volume_as_pct();
+    /// Moves the loudest channel to target and scales the others by the same ratio, so the
+    /// balance between channels is kept. This is what all of the volume changes go through.
+    /// The quieter channels are rounded rather than truncated, which is what lets an increase
+    /// followed by the same decrease land back on their exact raw values.
+    fn set_volume_to(&mut self, target: Volume) {
+        let volume = self.volume();
+        let mut volume = volume.borrow_mut();
+        let current = max_volume(&volume);
+
+        for vol in volume.get_mut().iter_mut() {
+            *vol = if current.is_muted() {
+                target
+            } else {
+                let current = u64::from(current.0);
+                let scaled = (u64::from(vol.0) * u64::from(target.0) + current / 2) / current;
+                Volume(scaled as u32)
+            };
+        }
+    }
 
-        match vol.cmp(&current_vol) {
-            Ordering::Less => {
-                let inc = current_vol - vol;
-                self.decrease_volume(&inc);
-            }
-            Ordering::Equal => {
-                println!("\nThe current volume is aleardy {vol}");
+    fn print_volume(&self, unit: VolumeUnit) {
+        let vol = VolumeSpec::from_volume(max_volume(&self.volume().borrow()), unit);
+        if self.mute() {
+            println!("\nThe current volume is: {vol} (muted)");
+        } else {
+            println!("\nThe current volume is: {vol}");
+        }
+    }
+
+    /// Lists the ports indented by indent. The active port is marked with a *, and the
+    /// jack state is shown when it is known.
+    fn print_ports(&self, indent: usize) {
+        for port in self.ports() {
+            let active = if self.active_port() == Some(port.name.as_str()) {
+                "*"
+            } else {
+                " "
+            };
+            let availability = match port.availability {
+                PortAvailability::Unknown => String::new(),
+                availability => format!(" ({availability})"),
+            };
+            println!(
+                "{:>indent$}    {active} {} -- {}{availability}",
+                "", port.name, port.description
+            );
+        }
+    }
+
+    /// Prints everything we know about the device, one field per line
+    fn print_info(&self, unit: VolumeUnit) {
+        let vol = VolumeSpec::from_volume(max_volume(&self.volume().borrow()), unit);
+        let base = VolumeSpec::from_volume(*self.base_volume().borrow(), unit);
+        let map = self.channel_map().print();
+
+        println!();
+        println!("{:>20}: {}", "name", self.name());
+        println!("{:>20}: {}", "index", self.index());
+        println!("{:>20}: {}", "description", self.description());
+        println!(
+            "{:>20}: {vol}{}",
+            "volume",
+            if self.mute() { " (muted)" } else { "" }
+        );
+        println!("{:>20}: {base}", "base volume");
+        println!("{:>20}: {map}", "channel map");
+
+        if let Some(metadata) = self.metadata() {
+            let optional = |value: Option<u32>| value.map_or("-".to_string(), |v| v.to_string());
+            println!("{:>20}: {}", "state", metadata.state);
+            println!("{:>20}: {}", "driver", metadata.driver);
+            println!(
+                "{:>20}: {}",
+                "owner module",
+                optional(metadata.owner_module)
+            );
+            println!("{:>20}: {}", "card", optional(metadata.card));
+            println!(
+                "{:>20}: {} {}ch {}Hz",
+                "sample spec", metadata.sample_format, metadata.channels, metadata.sample_rate
+            );
+            println!(
+                "{:>20}: {} usec (configured {} usec)",
+                "latency", metadata.latency_usec, metadata.configured_latency_usec
+            );
+            println!("{:>20}: {}", "volume steps", metadata.volume_steps);
+            if let Some(monitor) = &metadata.monitor {
+                println!("{:>20}: {monitor}", "monitor");
             }
-            Ordering::Greater => {
-                let inc = vol - current_vol;
-                self.increase_volume(&inc, boost);
+            println!("{:>20}: {}", "flags", metadata.flags.names().join(", "));
+            println!("{:>20}: {}", "formats", metadata.formats.join(", "));
+        }
+
+        if !self.ports().is_empty() {
+            println!("{:>20}:", "ports");
+            self.print_ports(18);
+        }
+
+        if let Some(metadata) = self.metadata() {
+            println!("{:>20}:", "properties");
+            for (key, value) in &metadata.properties {
+                println!("{:>20}    {key} = \"{value}\"", "");
             }
         }
     }
 
-    fn print_volume(&self) {
-        let vol = self.volume().borrow_mut().get()[0];
-        println!("\nThe current volume is: {}", vol.print());
+    /// Prints the volume of every channel, along with the balance and fade where the
+    /// channel map allows for them
+    fn print_channel_volumes(&self, unit: VolumeUnit) {
+        let map = *self.channel_map();
+        let volume = *self.volume().borrow();
+
+        println!();
+        for (position, vol) in map.get().iter().zip(volume.get()) {
+            let position = Position::to_string(*position).unwrap_or_default();
+            println!("{position:>20}: {}", VolumeSpec::from_volume(*vol, unit));
+        }
+        if map.can_balance() {
+            println!("{:>20}: {:.2}", "balance", volume.get_balance(&map));
+        }
+        if map.can_fade() {
+            println!("{:>20}: {:.2}", "fade", volume.get_fade(&map));
+        }
     }
 
-    // Made this for testing pulse_controller
+    /// The overall volume is the loudest channel, that is what Pulse reports as well
     fn get_volume_as_pct(&self) -> u8 {
-        let vol_str = self.volume().borrow_mut().get()[0].print();
-        for part in vol_str.split('%') {
-            if let Ok(num) = part.trim().parse::<u8>() {
-                return num;
+        read_volume_as_pct(max_volume(&self.volume().borrow()))
+    }
+
+    /// Gets the volume of the channels at position, i.e. front-left. If several channels share
+    /// the position, the loudest one is used, the same way the overall volume works.
+    fn get_channel_volume(&self, position: Position) -> Result<Volume, DeviceError> {
+        let volume = *self.volume().borrow();
+
+        self.channel_map()
+            .get()
+            .iter()
+            .zip(volume.get())
+            .filter(|(pos, _)| **pos == position)
+            .map(|(_, vol)| *vol)
+            .max()
+            .ok_or_else(|| channel_not_found(self.name(), position))
+    }
+
+    fn get_channel_volume_as_pct(&self, position: Position) -> Result<u8, DeviceError> {
+        Ok(read_volume_as_pct(self.get_channel_volume(position)?))
+    }
+
+    /// Sets the channels at position to target, the other channels are left alone
+    fn set_channel_volume_to(
+        &mut self,
+        position: Position,
+        target: Volume,
+    ) -> Result<(), DeviceError> {
+        let map = *self.channel_map();
+        let mut found = false;
+
+        for (pos, vol) in map
+            .get()
+            .iter()
+            .zip(self.volume().borrow_mut().get_mut().iter_mut())
+        {
+            if *pos == position {
+                *vol = target;
+                found = true;
             }
         }
-        255_u8
+
+        if found {
+            Ok(())
+        } else {
+            Err(channel_not_found(self.name(), position))
+        }
     }
 
-    fn read_tmp_vol(&self) -> std::io::Result<f64> {
-        let mut file = fs::File::open(FILE)?;
-        let mut vol_str = String::from("");
+    /// Sets the channels at position to vol, capped the same way set_volume is.
+    fn set_channel_volume(
+        &mut self,
+        position: Position,
+        vol: VolumeSpec,
+        boost: bool,
+    ) -> Result<(), DeviceError> {
+        let new_vol = vol.to_volume().min(self.volume_ceiling(boost));
+        self.set_channel_volume_to(position, new_vol)
+    }
 
-        file.read_to_string(&mut vol_str)?;
+    fn increase_channel_volume(
+        &mut self,
+        position: Position,
+        inc: VolumeSpec,
+        boost: bool,
+    ) -> Result<(), DeviceError> {
+        let current = self.get_channel_volume(position)?;
+        let new_vol = inc.raise(current).min(self.volume_ceiling(boost));
+
+        if new_vol > current {
+            self.set_channel_volume_to(position, new_vol)?;
+        }
+        Ok(())
+    }
 
-        for item in vol_str.split(' ') {
-            if let Ok(num) = item.parse::<f64>() {
-                return Ok(num);
-            }
+    fn decrease_channel_volume(
+        &mut self,
+        position: Position,
+        inc: VolumeSpec,
+    ) -> Result<(), DeviceError> {
+        let current = self.get_channel_volume(position)?;
+        let new_vol = inc.lower(current);
+
+        self.set_channel_volume_to(position, new_vol)
+    }
+
+    /// Shifts the volume between left (-1.0) and right (1.0) while keeping the overall level
+    fn set_balance(&mut self, balance: f32) -> Result<(), DeviceError> {
+        let map = *self.channel_map();
+
+        if !map.can_balance() {
+            return Err(DeviceError::ChannelNotFound(format!(
+                "{} has no left and right channels to balance",
+                self.name()
+            )));
         }
-        Err(Error::from(ErrorKind::InvalidData))
+
+        self.volume().borrow_mut().set_balance(&map, balance);
+        Ok(())
     }
 
-    fn toggle_mute(&mut self) -> std::io::Result<()> {
-        let channels = self.volume().borrow_mut().len();
+    /// Shifts the volume between rear (-1.0) and front (1.0) while keeping the overall level
+    fn set_fade(&mut self, fade: f32) -> Result<(), DeviceError> {
+        let map = *self.channel_map();
 
-        if self.volume().borrow_mut().is_muted() {
-            let vol_db = VolumeDB(self.read_tmp_vol()?);
-            self.volume()
-                .borrow_mut()
-                .set(channels, Volume::from(vol_db));
-        } else {
-            let mut file = fs::File::create(FILE)?;
-            let current_vol = self.volume().borrow_mut().print_db();
-            file.write_all(current_vol.as_bytes())?;
-            self.volume().borrow_mut().mute(channels);
+        if !map.can_fade() {
+            return Err(DeviceError::ChannelNotFound(format!(
+                "{} has no front and rear channels to fade",
+                self.name()
+            )));
         }
+
+        self.volume().borrow_mut().set_fade(&map, fade);
         Ok(())
     }
+
+    /// Muting uses the server's own mute flag, so the volume is left as is and comes
+    /// back unchanged when we unmute
+    fn toggle_mute(&mut self) {
+        let mute = !self.mute();
+        self.set_mute(mute);
+    }
+}
+
+/// Pulse reports percentages as a linear scale of the raw volume, rounded to the nearest
+/// percent. We do the same math here rather than parsing Volume::print.
+fn read_volume_as_pct(vol: Volume) -> u8 {
+    let pct =
+        (u64::from(vol.0) * 100 + u64::from(Volume::NORMAL.0) / 2) / u64::from(Volume::NORMAL.0);
+    pct.min(u64::from(u8::MAX)) as u8
+}
+
+/// The loudest channel, the same as ChannelVolumes::max without a trip through libpulse
+fn max_volume(volume: &ChannelVolumes) -> Volume {
+    volume.get().iter().copied().max().unwrap_or(Volume::MUTED)
+}
+
+fn channel_not_found(name: &str, position: Position) -> DeviceError {
+    let position = Position::to_string(position).unwrap_or_default();
+    DeviceError::ChannelNotFound(format!("{name} has no {position} channel"))
 }
 
 #[cfg(test)]
@@ -132,6 +431,9 @@ mod tests {
     struct MockDev {
         volume: Rc<RefCell<ChannelVolumes>>,
         base_volume: Rc<RefCell<Volume>>,
+        mute: bool,
+        channel_map: Map,
+        limits: VolumeLimits,
     }
 
     impl Device<MockDev> for MockDev {
@@ -154,6 +456,26 @@ mod tests {
         fn description(&self) -> &str {
             "Description"
         }
+
+        fn mute(&self) -> bool {
+            self.mute
+        }
+
+        fn set_mute(&mut self, mute: bool) {
+            self.mute = mute;
+        }
+
+        fn channel_map(&self) -> &Map {
+            &self.channel_map
+        }
+
+        fn limits(&self) -> VolumeLimits {
+            self.limits
+        }
+
+        fn set_limits(&mut self, limits: VolumeLimits) {
+            self.limits = limits;
+        }
     }
 
     fn setup() -> MockDev {
@@ -161,9 +483,16 @@ mod tests {
         let mut volume = ChannelVolumes::default();
         volume.set(2_u8, Volume::from(APPRROX_54_PCT));
         let volume = Rc::new(RefCell::new(volume));
+        let mut channel_map = Map::default();
+        channel_map.set_len(2);
+        channel_map.get_mut()[0] = Position::FrontLeft;
+        channel_map.get_mut()[1] = Position::FrontRight;
         MockDev {
             volume,
             base_volume,
+            mute: false,
+            channel_map,
+            limits: VolumeLimits::default(),
         }
     }
 
@@ -175,14 +504,12 @@ mod tests {
         assert_eq!(54_u8, result);
     }
 
-    // These pct I'm using ~54 and ~1 are just that. So we should test many values to know if we need to reduce
-    // thet DBs of ~1pct. It should work fine if ~1% is less than 1%
-    // Note: we are not counting the loops to see if we are using extra loops to get us to our final place, maybe we
-    // should.
+    // The ~54 pct we start from is just that, so these check we land on the percent we asked for
+    // from a volume that is not a whole percent.
     #[test]
     fn test_increase_vol_by_5() {
         let mut mock_dev = setup();
-        mock_dev.increase_volume(&5, false);
+        mock_dev.increase_volume(VolumeSpec::Percent(5.0), false);
 
         assert_eq!(59, mock_dev.get_volume_as_pct());
     }
@@ -190,7 +517,7 @@ mod tests {
     #[test]
     fn test_increase_vol_by_10() {
         let mut mock_dev = setup();
-        mock_dev.increase_volume(&10, false);
+        mock_dev.increase_volume(VolumeSpec::Percent(10.0), false);
 
         assert_eq!(64, mock_dev.get_volume_as_pct());
     }
@@ -198,7 +525,7 @@ mod tests {
     #[test]
     fn test_increase_vol_by_15() {
         let mut mock_dev = setup();
-        mock_dev.increase_volume(&15, false);
+        mock_dev.increase_volume(VolumeSpec::Percent(15.0), false);
 
         assert_eq!(69, mock_dev.get_volume_as_pct());
     }
@@ -206,7 +533,7 @@ mod tests {
     #[test]
     fn test_increase_vol_by_20() {
         let mut mock_dev = setup();
-        mock_dev.increase_volume(&20, false);
+        mock_dev.increase_volume(VolumeSpec::Percent(20.0), false);
 
         assert_eq!(74, mock_dev.get_volume_as_pct());
     }
@@ -214,7 +541,7 @@ mod tests {
     #[test]
     fn test_decrease_vol_by_5() {
         let mut mock_dev = setup();
-        mock_dev.decrease_volume(&5);
+        mock_dev.decrease_volume(VolumeSpec::Percent(5.0));
 
         assert_eq!(49, mock_dev.get_volume_as_pct());
     }
@@ -222,7 +549,7 @@ mod tests {
     #[test]
     fn test_decrease_vol_by_10() {
         let mut mock_dev = setup();
-        mock_dev.decrease_volume(&10);
+        mock_dev.decrease_volume(VolumeSpec::Percent(10.0));
 
         assert_eq!(44, mock_dev.get_volume_as_pct());
     }
@@ -230,7 +557,7 @@ mod tests {
     #[test]
     fn test_decrease_vol_by_15() {
         let mut mock_dev = setup();
-        mock_dev.decrease_volume(&15);
+        mock_dev.decrease_volume(VolumeSpec::Percent(15.0));
 
         assert_eq!(39, mock_dev.get_volume_as_pct());
     }
@@ -238,7 +565,7 @@ mod tests {
     #[test]
     fn test_decrease_vol_by_20() {
         let mut mock_dev = setup();
-        mock_dev.decrease_volume(&20);
+        mock_dev.decrease_volume(VolumeSpec::Percent(20.0));
 
         assert_eq!(34, mock_dev.get_volume_as_pct());
     }
@@ -247,7 +574,7 @@ mod tests {
     #[test]
     fn test_increase_vol_by_46() {
         let mut mock_dev = setup();
-        mock_dev.increase_volume(&46, false);
+        mock_dev.increase_volume(VolumeSpec::Percent(46.0), false);
 
         assert_eq!(MAX_VOLUME, mock_dev.get_volume_as_pct());
     }
@@ -255,7 +582,7 @@ mod tests {
     #[test]
     fn test_decrease_vol_by_54() {
         let mut mock_dev = setup();
-        mock_dev.decrease_volume(&54);
+        mock_dev.decrease_volume(VolumeSpec::Percent(54.0));
 
         assert_eq!(0, mock_dev.get_volume_as_pct());
     }
@@ -264,7 +591,7 @@ mod tests {
     #[test]
     fn test_increase_vol_by_56() {
         let mut mock_dev = setup();
-        mock_dev.increase_volume(&56, false);
+        mock_dev.increase_volume(VolumeSpec::Percent(56.0), false);
 
         assert_eq!(MAX_VOLUME, mock_dev.get_volume_as_pct());
     }
@@ -272,7 +599,7 @@ mod tests {
     #[test]
     fn test_decrease_vol_by_64() {
         let mut mock_dev = setup();
-        mock_dev.decrease_volume(&64);
+        mock_dev.decrease_volume(VolumeSpec::Percent(64.0));
 
         assert_eq!(0, mock_dev.get_volume_as_pct());
     }
@@ -281,7 +608,7 @@ mod tests {
     #[test]
     fn test_increase_vol_by_50() {
         let mut mock_dev = setup();
-        mock_dev.increase_volume(&50, true);
+        mock_dev.increase_volume(VolumeSpec::Percent(50.0), true);
 
         assert_eq!(104, mock_dev.get_volume_as_pct());
     }
@@ -290,7 +617,7 @@ mod tests {
     #[test]
     fn test_increase_vol_by_66() {
         let mut mock_dev = setup();
-        mock_dev.increase_volume(&66, true);
+        mock_dev.increase_volume(VolumeSpec::Percent(66.0), true);
 
         assert_eq!(MAX_VOLUME_BOOSTED, mock_dev.get_volume_as_pct());
     }
@@ -299,7 +626,7 @@ mod tests {
     #[test]
     fn test_increase_vol_by_255() {
         let mut mock_dev = setup();
-        mock_dev.increase_volume(&255, true);
+        mock_dev.increase_volume(VolumeSpec::Percent(255.0), true);
 
         assert_eq!(MAX_VOLUME_BOOSTED, mock_dev.get_volume_as_pct());
     }
@@ -310,7 +637,7 @@ mod tests {
         let vol = 65;
         let boost = false;
 
-        mock_dev.set_volume(vol, boost);
+        mock_dev.set_volume(VolumeSpec::Percent(f64::from(vol)), boost);
 
         assert_eq!(vol, mock_dev.get_volume_as_pct());
     }
@@ -321,7 +648,7 @@ mod tests {
         let vol = 150;
         let boost = false;
 
-        mock_dev.set_volume(vol, boost);
+        mock_dev.set_volume(VolumeSpec::Percent(f64::from(vol)), boost);
 
         // 100 is max without boost
         assert_eq!(MAX_VOLUME, mock_dev.get_volume_as_pct());
@@ -333,7 +660,7 @@ mod tests {
         let vol = 150;
         let boost = false;
 
-        mock_dev.set_volume(vol, boost);
+        mock_dev.set_volume(VolumeSpec::Percent(f64::from(vol)), boost);
 
         // 100 is max without boost
         assert_eq!(MAX_VOLUME, mock_dev.get_volume_as_pct());
@@ -345,8 +672,168 @@ mod tests {
         let vol = 20;
         let boost = false;
 
-        mock_dev.set_volume(vol, boost);
+        mock_dev.set_volume(VolumeSpec::Percent(f64::from(vol)), boost);
 
         assert_eq!(vol, mock_dev.get_volume_as_pct());
     }
+
+    #[test]
+    fn test_toggle_mute_keeps_volume() {
+        let mut mock_dev = setup();
+        let initial = mock_dev.get_volume_as_pct();
+
+        mock_dev.toggle_mute();
+
+        assert!(mock_dev.mute());
+        assert_eq!(initial, mock_dev.get_volume_as_pct());
+
+        mock_dev.toggle_mute();
+
+        assert!(!mock_dev.mute());
+        assert_eq!(initial, mock_dev.get_volume_as_pct());
+    }
+
+    #[test]
+    fn test_get_channel_volume_as_pct() {
+        let mock_dev = setup();
+
+        let result = mock_dev.get_channel_volume_as_pct(Position::FrontRight);
+
+        assert_eq!(54_u8, result.ok().unwrap());
+    }
+
+    #[test]
+    fn test_set_channel_volume_leaves_other_channels() {
+        let mut mock_dev = setup();
+
+        let res =
+            mock_dev.set_channel_volume(Position::FrontRight, VolumeSpec::Percent(30.0), false);
+
+        assert!(res.is_ok());
+        let left = mock_dev.get_channel_volume_as_pct(Position::FrontLeft);
+        let right = mock_dev.get_channel_volume_as_pct(Position::FrontRight);
+        assert_eq!(54, left.ok().unwrap());
+        assert_eq!(30, right.ok().unwrap());
+    }
+
+    #[test]
+    fn test_set_channel_volume_is_capped() {
+        let mut mock_dev = setup();
+
+        let res =
+            mock_dev.set_channel_volume(Position::FrontLeft, VolumeSpec::Percent(150.0), false);
+
+        assert!(res.is_ok());
+        let left = mock_dev.get_channel_volume_as_pct(Position::FrontLeft);
+        assert_eq!(MAX_VOLUME, left.ok().unwrap());
+    }
+
+    #[test]
+    fn test_missing_channel_is_an_error() {
+        let mut mock_dev = setup();
+
+        let res = mock_dev.increase_channel_volume(Position::Lfe, VolumeSpec::Percent(5.0), false);
+
+        assert!(matches!(res, Err(DeviceError::ChannelNotFound(_))));
+    }
+
+    #[test]
+    fn test_increase_vol_by_fraction() {
+        let mut mock_dev = setup();
+        let initial = mock_dev.volume().borrow().get()[0];
+
+        mock_dev.increase_volume(VolumeSpec::Percent(2.5), false);
+
+        let raw = mock_dev.volume().borrow().get()[0];
+        assert_eq!(initial.0 + 1638, raw.0);
+    }
+
+    #[test]
+    fn test_increase_then_decrease_is_exact() {
+        let mut mock_dev = setup();
+        let initial = *mock_dev.volume().borrow();
+
+        mock_dev.increase_volume(VolumeSpec::Percent(7.3), false);
+        mock_dev.decrease_volume(VolumeSpec::Percent(7.3));
+
+        assert_eq!(initial.get(), mock_dev.volume().borrow().get());
+    }
+
+    #[test]
+    fn test_increase_then_decrease_is_exact_when_unbalanced() {
+        let mut mock_dev = setup();
+        mock_dev.set_balance(0.37).ok().unwrap();
+        let initial = *mock_dev.volume().borrow();
+        assert_ne!(initial.get()[0], initial.get()[1]);
+
+        mock_dev.increase_volume(VolumeSpec::Percent(7.3), false);
+        mock_dev.decrease_volume(VolumeSpec::Percent(7.3));
+
+        assert_eq!(initial.get(), mock_dev.volume().borrow().get());
+    }
+
+    #[test]
+    fn test_set_volume_in_raw_units() {
+        let mut mock_dev = setup();
+
+        mock_dev.set_volume(VolumeSpec::Raw(32768), false);
+
+        assert_eq!(50, mock_dev.get_volume_as_pct());
+    }
+
+    #[test]
+    fn test_increase_keeps_boosted_volume() {
+        let mut mock_dev = setup();
+        mock_dev.set_volume(VolumeSpec::Percent(110.0), true);
+
+        mock_dev.increase_volume(VolumeSpec::Percent(5.0), false);
+
+        assert_eq!(110, mock_dev.get_volume_as_pct());
+    }
+
+    #[test]
+    fn test_change_keeps_channel_ratio() {
+        let mut mock_dev = setup();
+        mock_dev
+            .set_channel_volume(Position::FrontRight, VolumeSpec::Percent(27.0), false)
+            .ok()
+            .unwrap();
+
+        mock_dev.set_volume(VolumeSpec::Percent(100.0), false);
+
+        let left = mock_dev.get_channel_volume_as_pct(Position::FrontLeft);
+        let right = mock_dev.get_channel_volume_as_pct(Position::FrontRight);
+        assert_eq!(100, left.ok().unwrap());
+        assert_eq!(50, right.ok().unwrap());
+    }
+
+    #[test]
+    fn test_custom_limits_cap_the_volume() {
+        let mut mock_dev = setup();
+        mock_dev.set_limits(VolumeLimits::new(80.0, 150.0, false));
+
+        mock_dev.set_volume(VolumeSpec::Percent(100.0), false);
+        assert_eq!(80, mock_dev.get_volume_as_pct());
+
+        mock_dev.set_volume(VolumeSpec::Percent(140.0), true);
+        assert_eq!(140, mock_dev.get_volume_as_pct());
+    }
+
+    #[test]
+    fn test_limits_relative_to_base_volume() {
+        let mut mock_dev = setup();
+        let base_volume = *mock_dev.base_volume().borrow();
+        mock_dev.set_limits(VolumeLimits::new(100.0, 120.0, true));
+
+        assert_eq!(base_volume, mock_dev.volume_ceiling(false));
+        mock_dev.set_volume(VolumeSpec::Percent(100.0), false);
+        assert_eq!(base_volume, max_volume(&mock_dev.volume().borrow()));
+    }
+
+    #[test]
+    fn test_limits_never_pass_pulse_max() {
+        let limits = VolumeLimits::new(f64::MAX, f64::MAX, false);
+
+        assert_eq!(Volume::MAX, limits.ceiling(true, Volume::NORMAL));
+    }
 }
@@ -0,0 +1,220 @@
+//! Everything else Pulse tells us about a sink or source, past what the volume controls need.
+//! This is only shown by the info command, the rest of rupamix doesn't look at it.
+use pulse::context::introspect::{SinkInfo, SourceInfo};
+use pulse::def::{SinkFlagSet, SinkState, SourceFlagSet, SourceState};
+use pulse::format;
+use pulse::proplist::Proplist;
+use pulse::sample::Spec;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DeviceState {
+    Running,
+    Idle,
+    Suspended,
+    /// The server doesn't support state introspection
+    #[default]
+    Unknown,
+}
+
+impl From<SinkState> for DeviceState {
+    fn from(state: SinkState) -> Self {
+        match state {
+            SinkState::Running => DeviceState::Running,
+            SinkState::Idle => DeviceState::Idle,
+            SinkState::Suspended => DeviceState::Suspended,
+            _ => DeviceState::Unknown,
+        }
+    }
+}
+
+impl From<SourceState> for DeviceState {
+    fn from(state: SourceState) -> Self {
+        match state {
+            SourceState::Running => DeviceState::Running,
+            SourceState::Idle => DeviceState::Idle,
+            SourceState::Suspended => DeviceState::Suspended,
+            _ => DeviceState::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for DeviceState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = match self {
+            DeviceState::Running => "running",
+            DeviceState::Idle => "idle",
+            DeviceState::Suspended => "suspended",
+            DeviceState::Unknown => "unknown",
+        };
+        write!(f, "{state}")
+    }
+}
+
+/// The sink and source flags, which are the same apart from their types
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize)]
+pub struct DeviceFlags {
+    pub hardware: bool,
+    pub network: bool,
+    pub hardware_volume: bool,
+    pub hardware_mute: bool,
+    pub decibel_volume: bool,
+    pub flat_volume: bool,
+    pub latency: bool,
+    pub dynamic_latency: bool,
+}
+
+impl From<SinkFlagSet> for DeviceFlags {
+    fn from(flags: SinkFlagSet) -> Self {
+        DeviceFlags {
+            hardware: flags.contains(SinkFlagSet::HARDWARE),
+            network: flags.contains(SinkFlagSet::NETWORK),
+            hardware_volume: flags.contains(SinkFlagSet::HW_VOLUME_CTRL),
+            hardware_mute: flags.contains(SinkFlagSet::HW_MUTE_CTRL),
+            decibel_volume: flags.contains(SinkFlagSet::DECIBEL_VOLUME),
+            flat_volume: flags.contains(SinkFlagSet::FLAT_VOLUME),
+            latency: flags.contains(SinkFlagSet::LATENCY),
+            dynamic_latency: flags.contains(SinkFlagSet::DYNAMIC_LATENCY),
+        }
+    }
+}
+
+impl From<SourceFlagSet> for DeviceFlags {
+    fn from(flags: SourceFlagSet) -> Self {
+        DeviceFlags {
+            hardware: flags.contains(SourceFlagSet::HARDWARE),
+            network: flags.contains(SourceFlagSet::NETWORK),
+            hardware_volume: flags.contains(SourceFlagSet::HW_VOLUME_CTRL),
+            hardware_mute: flags.contains(SourceFlagSet::HW_MUTE_CTRL),
+            decibel_volume: flags.contains(SourceFlagSet::DECIBEL_VOLUME),
+            // Sources have no flat volume mode
+            flat_volume: false,
+            latency: flags.contains(SourceFlagSet::LATENCY),
+            dynamic_latency: flags.contains(SourceFlagSet::DYNAMIC_LATENCY),
+        }
+    }
+}
+
+impl DeviceFlags {
+    /// The names of the flags that are set, for printing
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.hardware, "hardware"),
+            (self.network, "network"),
+            (self.hardware_volume, "hardware volume"),
+            (self.hardware_mute, "hardware mute"),
+            (self.decibel_volume, "decibel volume"),
+            (self.flat_volume, "flat volume"),
+            (self.latency, "latency"),
+            (self.dynamic_latency, "dynamic latency"),
+        ]
+        .into_iter()
+        .filter_map(|(set, name)| set.then_some(name))
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DeviceMetadata {
+    pub state: DeviceState,
+    pub driver: String,
+    pub owner_module: Option<u32>,
+    pub card: Option<u32>,
+    /// i.e. s16le
+    pub sample_format: String,
+    pub sample_rate: u32,
+    pub channels: u8,
+    pub latency_usec: u64,
+    pub configured_latency_usec: u64,
+    pub volume_steps: u32,
+    /// For a sink its monitor source, for a monitor source the sink it monitors
+    pub monitor: Option<String>,
+    pub flags: DeviceFlags,
+    pub formats: Vec<String>,
+    pub properties: BTreeMap<String, String>,
+}
+
+impl From<&'_ SinkInfo<'_>> for DeviceMetadata {
+    fn from(item: &SinkInfo) -> Self {
+        DeviceMetadata {
+            state: DeviceState::from(item.state),
+            driver: item.driver.clone().map(String::from).unwrap_or_default(),
+            owner_module: item.owner_module,
+            card: item.card,
+            sample_format: sample_format(&item.sample_spec),
+            sample_rate: item.sample_spec.rate,
+            channels: item.sample_spec.channels,
+            latency_usec: item.latency.0,
+            configured_latency_usec: item.configured_latency.0,
+            volume_steps: item.n_volume_steps,
+            monitor: item.monitor_source_name.clone().map(String::from),
+            flags: DeviceFlags::from(item.flags),
+            formats: formats(&item.formats),
+            properties: properties(&item.proplist),
+        }
+    }
+}
+
+impl From<&'_ SourceInfo<'_>> for DeviceMetadata {
+    fn from(item: &SourceInfo) -> Self {
+        DeviceMetadata {
+            state: DeviceState::from(item.state),
+            driver: item.driver.clone().map(String::from).unwrap_or_default(),
+            owner_module: item.owner_module,
+            card: item.card,
+            sample_format: sample_format(&item.sample_spec),
+            sample_rate: item.sample_spec.rate,
+            channels: item.sample_spec.channels,
+            latency_usec: item.latency.0,
+            configured_latency_usec: item.configured_latency.0,
+            volume_steps: item.n_volume_steps,
+            monitor: item.monitor_of_sink_name.clone().map(String::from),
+            flags: DeviceFlags::from(item.flags),
+            formats: formats(&item.formats),
+            properties: properties(&item.proplist),
+        }
+    }
+}
+
+fn sample_format(spec: &Spec) -> String {
+    spec.format
+        .to_string()
+        .map(String::from)
+        .unwrap_or_default()
+}
+
+fn formats(formats: &[format::Info]) -> Vec<String> {
+    formats.iter().map(format::Info::print).collect()
+}
+
+fn properties(proplist: &Proplist) -> BTreeMap<String, String> {
+    proplist
+        .iter()
+        .filter_map(|key| proplist.get_str(&key).map(|value| (key, value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags() {
+        let flags = DeviceFlags::from(SinkFlagSet::HARDWARE | SinkFlagSet::DECIBEL_VOLUME);
+
+        assert_eq!(vec!["hardware", "decibel volume"], flags.names());
+        assert!(DeviceFlags::from(SourceFlagSet::NOFLAGS).names().is_empty());
+    }
+
+    #[test]
+    fn test_state() {
+        assert_eq!(DeviceState::Idle, DeviceState::from(SinkState::Idle));
+        assert_eq!(
+            DeviceState::Unknown,
+            DeviceState::from(SourceState::Invalid)
+        );
+    }
+}
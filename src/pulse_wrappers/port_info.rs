@@ -0,0 +1,74 @@
+//! The ports of a sink or source, i.e. speakers and a headphone jack on the same output.
+//! Only one port is active at a time, and that decides where the sound actually goes.
+use pulse::context::introspect::{SinkPortInfo, SourcePortInfo};
+use pulse::def::PortAvailable;
+use serde::Serialize;
+use std::fmt;
+
+/// Whether something is plugged into the port. Ports without jack detection are Unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PortAvailability {
+    Unknown,
+    Unplugged,
+    Plugged,
+}
+
+impl From<PortAvailable> for PortAvailability {
+    fn from(available: PortAvailable) -> Self {
+        match available {
+            PortAvailable::Unknown => PortAvailability::Unknown,
+            PortAvailable::No => PortAvailability::Unplugged,
+            PortAvailable::Yes => PortAvailability::Plugged,
+        }
+    }
+}
+
+impl fmt::Display for PortAvailability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let availability = match self {
+            PortAvailability::Unknown => "unknown",
+            PortAvailability::Unplugged => "unplugged",
+            PortAvailability::Plugged => "plugged in",
+        };
+        write!(f, "{availability}")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PulsePortInfo {
+    pub name: String,
+    pub description: String,
+    pub priority: u32,
+    pub availability: PortAvailability,
+}
+
+impl From<&'_ SinkPortInfo<'_>> for PulsePortInfo {
+    fn from(item: &SinkPortInfo) -> Self {
+        PulsePortInfo {
+            name: item.name.clone().map(String::from).unwrap_or_default(),
+            description: item
+                .description
+                .clone()
+                .map(String::from)
+                .unwrap_or_default(),
+            priority: item.priority,
+            availability: PortAvailability::from(item.available),
+        }
+    }
+}
+
+impl From<&'_ SourcePortInfo<'_>> for PulsePortInfo {
+    fn from(item: &SourcePortInfo) -> Self {
+        PulsePortInfo {
+            name: item.name.clone().map(String::from).unwrap_or_default(),
+            description: item
+                .description
+                .clone()
+                .map(String::from)
+                .unwrap_or_default(),
+            priority: item.priority,
+            availability: PortAvailability::from(item.available),
+        }
+    }
+}
@@ -2,7 +2,7 @@
 /// We will just make this object to store the data we want
 use pulse::context::introspect::ServerInfo;
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct PulseServerInfo {
     pub default_source_name: String,
     pub default_sink_name: String,
@@ -10,15 +10,22 @@ pub struct PulseServerInfo {
 
 impl PulseServerInfo {
     pub fn update(&mut self, info: &'_ ServerInfo<'_>) {
-        self.default_source_name = info.default_source_name.as_ref().unwrap().to_string();
-        self.default_sink_name = info.default_sink_name.as_ref().unwrap().to_string();
+        *self = PulseServerInfo::from(info);
     }
 }
 
 impl From<&'_ ServerInfo<'_>> for PulseServerInfo {
     fn from(info: &ServerInfo) -> Self {
-        let default_source_name = info.default_source_name.as_ref().unwrap().to_string();
-        let default_sink_name = info.default_sink_name.as_ref().unwrap().to_string();
+        let default_source_name = info
+            .default_source_name
+            .clone()
+            .map(String::from)
+            .unwrap_or_default();
+        let default_sink_name = info
+            .default_sink_name
+            .clone()
+            .map(String::from)
+            .unwrap_or_default();
 
         PulseServerInfo {
             default_sink_name,
@@ -0,0 +1,150 @@
+use crate::pulse_wrappers::device::{Device, VolumeLimits};
+use pulse::channelmap::Map;
+use pulse::context::introspect::SinkInputInfo;
+use pulse::proplist::properties;
+use pulse::volume::{ChannelVolumes, Volume};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A sink input is a playback stream owned by an application, i.e. your music player.
+/// Streams do not report a base volume, so we use Volume::NORMAL for it.
+pub struct PulseSinkInputInfo {
+    name: String,
+    index: u32,
+    application_name: String,
+    process_binary: String,
+    sink: u32,
+    mute: bool,
+    volume: Rc<RefCell<ChannelVolumes>>,
+    base_volume: Rc<RefCell<Volume>>,
+    channel_map: Map,
+    limits: VolumeLimits,
+}
+
+/// The volume is copied rather than shared, see PulseSinkInfo
+impl Clone for PulseSinkInputInfo {
+    fn clone(&self) -> Self {
+        PulseSinkInputInfo {
+            name: self.name.clone(),
+            index: self.index,
+            application_name: self.application_name.clone(),
+            process_binary: self.process_binary.clone(),
+            sink: self.sink,
+            mute: self.mute,
+            volume: Rc::new(RefCell::new(*self.volume.borrow())),
+            base_volume: Rc::new(RefCell::new(*self.base_volume.borrow())),
+            channel_map: self.channel_map,
+            limits: self.limits,
+        }
+    }
+}
+
+impl PulseSinkInputInfo {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: String,
+        index: u32,
+        application_name: String,
+        process_binary: String,
+        sink: u32,
+        mute: bool,
+        volume: Rc<RefCell<ChannelVolumes>>,
+        channel_map: Map,
+    ) -> PulseSinkInputInfo {
+        PulseSinkInputInfo {
+            name,
+            index,
+            application_name,
+            process_binary,
+            sink,
+            mute,
+            volume,
+            base_volume: Rc::new(RefCell::new(Volume::NORMAL)),
+            channel_map,
+            limits: VolumeLimits::default(),
+        }
+    }
+
+    /// The name the application gave itself, i.e. "Firefox"
+    pub fn application_name(&self) -> &str {
+        &self.application_name
+    }
+
+    /// The name of the binary that owns the stream, i.e. "firefox"
+    pub fn process_binary(&self) -> &str {
+        &self.process_binary
+    }
+
+    /// The index of the sink this stream is playing on
+    pub fn sink(&self) -> u32 {
+        self.sink
+    }
+}
+
+impl From<&'_ SinkInputInfo<'_>> for PulseSinkInputInfo {
+    fn from(item: &SinkInputInfo) -> Self {
+        let name = item.name.clone().map(String::from).unwrap_or_default();
+        let application_name = item
+            .proplist
+            .get_str(properties::APPLICATION_NAME)
+            .unwrap_or_default();
+        let process_binary = item
+            .proplist
+            .get_str(properties::APPLICATION_PROCESS_BINARY)
+            .unwrap_or_default();
+
+        PulseSinkInputInfo::new(
+            name,
+            item.index,
+            application_name,
+            process_binary,
+            item.sink,
+            item.mute,
+            Rc::new(RefCell::new(item.volume)),
+            item.channel_map,
+        )
+    }
+}
+
+impl Device<PulseSinkInputInfo> for PulseSinkInputInfo {
+    fn index(&self) -> u32 {
+        self.index
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn volume(&self) -> Rc<RefCell<ChannelVolumes>> {
+        self.volume.clone()
+    }
+
+    fn base_volume(&self) -> Rc<RefCell<Volume>> {
+        self.base_volume.clone()
+    }
+
+    /// Streams don't have a description, the application name is the closest thing
+    fn description(&self) -> &str {
+        &self.application_name
+    }
+
+    fn mute(&self) -> bool {
+        self.mute
+    }
+
+    fn set_mute(&mut self, mute: bool) {
+        self.mute = mute;
+    }
+
+    fn channel_map(&self) -> &Map {
+        &self.channel_map
+    }
+
+    fn limits(&self) -> VolumeLimits {
+        self.limits
+    }
+
+    fn set_limits(&mut self, limits: VolumeLimits) {
+        self.limits = limits;
+    }
+}
@@ -1,4 +1,7 @@
-use crate::pulse_wrappers::device::Device;
+use crate::pulse_wrappers::device::{Device, VolumeLimits};
+use crate::pulse_wrappers::device_metadata::DeviceMetadata;
+use crate::pulse_wrappers::port_info::PulsePortInfo;
+use pulse::channelmap::Map;
 use pulse::context::introspect::SourceInfo;
 use pulse::volume::{ChannelVolumes, Volume};
 use std::cell::RefCell;
@@ -10,6 +13,31 @@ pub struct PulseSourceInfo {
     description: String,
     volume: Rc<RefCell<ChannelVolumes>>,
     base_volume: Rc<RefCell<Volume>>,
+    mute: bool,
+    channel_map: Map,
+    limits: VolumeLimits,
+    ports: Vec<PulsePortInfo>,
+    active_port: Option<String>,
+    metadata: DeviceMetadata,
+}
+
+/// Same as PulseSinkInfo, the clone has a volume of its own
+impl Clone for PulseSourceInfo {
+    fn clone(&self) -> Self {
+        PulseSourceInfo {
+            name: self.name.clone(),
+            index: self.index,
+            description: self.description.clone(),
+            volume: Rc::new(RefCell::new(*self.volume.borrow())),
+            base_volume: Rc::new(RefCell::new(*self.base_volume.borrow())),
+            mute: self.mute,
+            channel_map: self.channel_map,
+            limits: self.limits,
+            ports: self.ports.clone(),
+            active_port: self.active_port.clone(),
+            metadata: self.metadata.clone(),
+        }
+    }
 }
 
 impl PulseSourceInfo {
@@ -19,6 +47,8 @@ impl PulseSourceInfo {
         description: String,
         volume: Rc<RefCell<ChannelVolumes>>,
         base_volume: Rc<RefCell<Volume>>,
+        mute: bool,
+        channel_map: Map,
     ) -> PulseSourceInfo {
         PulseSourceInfo {
             name,
@@ -26,18 +56,43 @@ impl PulseSourceInfo {
             description,
             volume,
             base_volume,
+            mute,
+            channel_map,
+            limits: VolumeLimits::default(),
+            ports: Vec::new(),
+            active_port: None,
+            metadata: DeviceMetadata::default(),
         }
     }
+
+    pub fn set_ports(&mut self, ports: Vec<PulsePortInfo>, active_port: Option<String>) {
+        self.ports = ports;
+        self.active_port = active_port;
+    }
 }
 
 impl From<&'_ SourceInfo<'_>> for PulseSourceInfo {
     fn from(item: &SourceInfo) -> Self {
         PulseSourceInfo {
-            name: String::from(item.name.clone().unwrap()),
+            name: item.name.clone().map(String::from).unwrap_or_default(),
             index: item.index,
-            description: String::from(item.description.clone().unwrap()),
+            description: item
+                .description
+                .clone()
+                .map(String::from)
+                .unwrap_or_default(),
             volume: Rc::new(RefCell::new(item.volume)),
             base_volume: Rc::new(RefCell::new(item.base_volume)),
+            mute: item.mute,
+            channel_map: item.channel_map,
+            limits: VolumeLimits::default(),
+            ports: item.ports.iter().map(PulsePortInfo::from).collect(),
+            active_port: item
+                .active_port
+                .as_ref()
+                .and_then(|port| port.name.clone())
+                .map(String::from),
+            metadata: DeviceMetadata::from(item),
         }
     }
 }
@@ -62,4 +117,36 @@ impl Device<PulseSourceInfo> for PulseSourceInfo {
     fn description(&self) -> &str {
         &self.description
     }
+
+    fn mute(&self) -> bool {
+        self.mute
+    }
+
+    fn set_mute(&mut self, mute: bool) {
+        self.mute = mute;
+    }
+
+    fn channel_map(&self) -> &Map {
+        &self.channel_map
+    }
+
+    fn limits(&self) -> VolumeLimits {
+        self.limits
+    }
+
+    fn set_limits(&mut self, limits: VolumeLimits) {
+        self.limits = limits;
+    }
+
+    fn ports(&self) -> &[PulsePortInfo] {
+        &self.ports
+    }
+
+    fn active_port(&self) -> Option<&str> {
+        self.active_port.as_deref()
+    }
+
+    fn metadata(&self) -> Option<&DeviceMetadata> {
+        Some(&self.metadata)
+    }
 }
@@ -0,0 +1,150 @@
+use crate::pulse_wrappers::device::{Device, VolumeLimits};
+use pulse::channelmap::Map;
+use pulse::context::introspect::SourceOutputInfo;
+use pulse::proplist::properties;
+use pulse::volume::{ChannelVolumes, Volume};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A source output is a recording stream owned by an application, i.e. your voice chat.
+/// Streams do not report a base volume, so we use Volume::NORMAL for it.
+pub struct PulseSourceOutputInfo {
+    name: String,
+    index: u32,
+    application_name: String,
+    process_binary: String,
+    source: u32,
+    mute: bool,
+    volume: Rc<RefCell<ChannelVolumes>>,
+    base_volume: Rc<RefCell<Volume>>,
+    channel_map: Map,
+    limits: VolumeLimits,
+}
+
+/// Copies the volume too, so the clone can be changed on its own
+impl Clone for PulseSourceOutputInfo {
+    fn clone(&self) -> Self {
+        PulseSourceOutputInfo {
+            name: self.name.clone(),
+            index: self.index,
+            application_name: self.application_name.clone(),
+            process_binary: self.process_binary.clone(),
+            source: self.source,
+            mute: self.mute,
+            volume: Rc::new(RefCell::new(*self.volume.borrow())),
+            base_volume: Rc::new(RefCell::new(*self.base_volume.borrow())),
+            channel_map: self.channel_map,
+            limits: self.limits,
+        }
+    }
+}
+
+impl PulseSourceOutputInfo {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        name: String,
+        index: u32,
+        application_name: String,
+        process_binary: String,
+        source: u32,
+        mute: bool,
+        volume: Rc<RefCell<ChannelVolumes>>,
+        channel_map: Map,
+    ) -> PulseSourceOutputInfo {
+        PulseSourceOutputInfo {
+            name,
+            index,
+            application_name,
+            process_binary,
+            source,
+            mute,
+            volume,
+            base_volume: Rc::new(RefCell::new(Volume::NORMAL)),
+            channel_map,
+            limits: VolumeLimits::default(),
+        }
+    }
+
+    /// The name the application gave itself, i.e. "Firefox"
+    pub fn application_name(&self) -> &str {
+        &self.application_name
+    }
+
+    /// The name of the binary that owns the stream, i.e. "firefox"
+    pub fn process_binary(&self) -> &str {
+        &self.process_binary
+    }
+
+    /// The index of the source this stream is recording from
+    pub fn source(&self) -> u32 {
+        self.source
+    }
+}
+
+impl From<&'_ SourceOutputInfo<'_>> for PulseSourceOutputInfo {
+    fn from(item: &SourceOutputInfo) -> Self {
+        let name = item.name.clone().map(String::from).unwrap_or_default();
+        let application_name = item
+            .proplist
+            .get_str(properties::APPLICATION_NAME)
+            .unwrap_or_default();
+        let process_binary = item
+            .proplist
+            .get_str(properties::APPLICATION_PROCESS_BINARY)
+            .unwrap_or_default();
+
+        PulseSourceOutputInfo::new(
+            name,
+            item.index,
+            application_name,
+            process_binary,
+            item.source,
+            item.mute,
+            Rc::new(RefCell::new(item.volume)),
+            item.channel_map,
+        )
+    }
+}
+
+impl Device<PulseSourceOutputInfo> for PulseSourceOutputInfo {
+    fn index(&self) -> u32 {
+        self.index
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn volume(&self) -> Rc<RefCell<ChannelVolumes>> {
+        self.volume.clone()
+    }
+
+    fn base_volume(&self) -> Rc<RefCell<Volume>> {
+        self.base_volume.clone()
+    }
+
+    /// Streams don't have a description, the application name is the closest thing
+    fn description(&self) -> &str {
+        &self.application_name
+    }
+
+    fn mute(&self) -> bool {
+        self.mute
+    }
+
+    fn set_mute(&mut self, mute: bool) {
+        self.mute = mute;
+    }
+
+    fn channel_map(&self) -> &Map {
+        &self.channel_map
+    }
+
+    fn limits(&self) -> VolumeLimits {
+        self.limits
+    }
+
+    fn set_limits(&mut self, limits: VolumeLimits) {
+        self.limits = limits;
+    }
+}
@@ -0,0 +1,247 @@
+//! Pulse has several ways of looking at a volume: the raw Volume it works with, a percentage
+//! which is linear in raw units, decibels, and a linear amplitude factor. VolumeSpec is a volume,
+//! or a step in volume, in whichever of those the user gave it to us.
+use pulse::volume::{Volume, VolumeDB, VolumeLinear};
+use std::fmt;
+use std::str::FromStr;
+
+/// Steps up in dB or as a factor start from here when the volume is any quieter. A muted
+/// volume is -inf dB, and no number of those steps would ever get it anywhere.
+pub static DECIBEL_FLOOR: f64 = -60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolumeSpec {
+    /// 50 or 50%
+    Percent(f64),
+    /// -6dB
+    Decibel(f64),
+    /// 0.5x
+    Linear(f64),
+    /// 32768raw
+    Raw(u32),
+}
+
+/// The unit to show a volume in, see VolumeSpec
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VolumeUnit {
+    #[default]
+    Percent,
+    Decibel,
+    Linear,
+    Raw,
+}
+
+impl VolumeSpec {
+    /// The absolute Volume this spec stands for
+    pub fn to_volume(&self) -> Volume {
+        match *self {
+            VolumeSpec::Percent(pct) => Volume(raw_from_pct(pct)),
+            VolumeSpec::Decibel(db) => Volume::from(VolumeDB(db)),
+            VolumeSpec::Linear(factor) => Volume::from(VolumeLinear(factor.max(0.0))),
+            VolumeSpec::Raw(raw) => Volume(raw),
+        }
+        .min(Volume::MAX)
+    }
+
+    /// Treats the spec as a step and raises current by it. Percent and raw steps are done in
+    /// raw units, so raising and then lowering by the same step gets back the exact same Volume.
+    /// dB and factor steps up from below DECIBEL_FLOOR, i.e. from muted, start at the floor.
+    pub fn raise(&self, current: Volume) -> Volume {
+        let raised = match *self {
+            VolumeSpec::Percent(pct) => Volume(current.0.saturating_add(raw_from_pct(pct))),
+            VolumeSpec::Raw(raw) => Volume(current.0.saturating_add(raw)),
+            VolumeSpec::Decibel(db) if db > 0.0 => Volume::from(VolumeDB(floored(current).0 + db)),
+            VolumeSpec::Decibel(db) => Volume::from(VolumeDB(VolumeDB::from(current).0 + db)),
+            VolumeSpec::Linear(factor) if factor > 1.0 => Volume::from(VolumeLinear(
+                VolumeLinear::from(floored(current)).0 * factor,
+            )),
+            VolumeSpec::Linear(factor) => Volume::from(VolumeLinear(
+                VolumeLinear::from(current).0 * factor.max(0.0),
+            )),
+        };
+        raised.min(Volume::MAX)
+    }
+
+    /// Treats the spec as a step and lowers current by it, see raise
+    pub fn lower(&self, current: Volume) -> Volume {
+        match *self {
+            VolumeSpec::Percent(pct) => Volume(current.0.saturating_sub(raw_from_pct(pct))),
+            VolumeSpec::Raw(raw) => Volume(current.0.saturating_sub(raw)),
+            VolumeSpec::Decibel(db) => Volume::from(VolumeDB(VolumeDB::from(current).0 - db)),
+            VolumeSpec::Linear(factor) if factor > 0.0 => {
+                Volume::from(VolumeLinear(VolumeLinear::from(current).0 / factor))
+            }
+            VolumeSpec::Linear(_) => Volume::MUTED,
+        }
+    }
+
+    /// Expresses volume in the given unit, mostly so it can be printed
+    pub fn from_volume(volume: Volume, unit: VolumeUnit) -> VolumeSpec {
+        match unit {
+            VolumeUnit::Percent => {
+                VolumeSpec::Percent(f64::from(volume.0) * 100.0 / f64::from(Volume::NORMAL.0))
+            }
+            VolumeUnit::Decibel => VolumeSpec::Decibel(VolumeDB::from(volume).0),
+            VolumeUnit::Linear => VolumeSpec::Linear(VolumeLinear::from(volume).0),
+            VolumeUnit::Raw => VolumeSpec::Raw(volume.0),
+        }
+    }
+
+    /// Whether this does anything at all as a step
+    pub fn is_zero(&self) -> bool {
+        match *self {
+            VolumeSpec::Percent(value) | VolumeSpec::Decibel(value) => value == 0.0,
+            VolumeSpec::Linear(value) => value == 1.0,
+            VolumeSpec::Raw(value) => value == 0,
+        }
+    }
+}
+
+/// current in dB, but never below DECIBEL_FLOOR
+fn floored(current: Volume) -> VolumeDB {
+    VolumeDB(VolumeDB::from(current).0.max(DECIBEL_FLOOR))
+}
+
+/// The raw size of pct percent, Pulse's percentages are linear in raw units
+fn raw_from_pct(pct: f64) -> u32 {
+    (pct.max(0.0) * f64::from(Volume::NORMAL.0) / 100.0).round() as u32
+}
+
+impl FromStr for VolumeSpec {
+    type Err = String;
+
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let spec = spec.trim();
+        let lower = spec.to_ascii_lowercase();
+
+        let parsed = if let Some(db) = lower.strip_suffix("db") {
+            parse_finite(db).map(VolumeSpec::Decibel)
+        } else if let Some(raw) = lower.strip_suffix("raw") {
+            raw.trim().parse::<u32>().ok().map(VolumeSpec::Raw)
+        } else if let Some(factor) = lower.strip_suffix('x') {
+            parse_finite(factor)
+                .filter(|factor| *factor >= 0.0)
+                .map(VolumeSpec::Linear)
+        } else {
+            parse_finite(lower.strip_suffix('%').unwrap_or(&lower))
+                .filter(|pct| *pct >= 0.0)
+                .map(VolumeSpec::Percent)
+        };
+
+        parsed
+            .ok_or_else(|| format!("{spec} is not a volume, try 50 or 50%, -6dB, 0.5x or 32768raw"))
+    }
+}
+
+fn parse_finite(number: &str) -> Option<f64> {
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+}
+
+impl fmt::Display for VolumeSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VolumeSpec::Percent(pct) => write!(f, "{}%", pct.round()),
+            VolumeSpec::Decibel(db) if db == f64::NEG_INFINITY => write!(f, "-inf dB"),
+            VolumeSpec::Decibel(db) => write!(f, "{db:.2} dB"),
+            VolumeSpec::Linear(factor) => write!(f, "{factor:.3}x"),
+            VolumeSpec::Raw(raw) => write!(f, "{raw}raw"),
+        }
+    }
+}
+
+impl FromStr for VolumeUnit {
+    type Err = String;
+
+    fn from_str(unit: &str) -> Result<Self, Self::Err> {
+        match unit.to_ascii_lowercase().as_str() {
+            "pct" | "percent" | "%" => Ok(VolumeUnit::Percent),
+            "db" | "decibel" => Ok(VolumeUnit::Decibel),
+            "linear" | "x" => Ok(VolumeUnit::Linear),
+            "raw" => Ok(VolumeUnit::Raw),
+            _ => Err(format!("{unit} is not a unit, try pct, db, linear or raw")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_percent() {
+        assert_eq!(Ok(VolumeSpec::Percent(50.0)), "50".parse());
+        assert_eq!(Ok(VolumeSpec::Percent(2.5)), "2.5%".parse());
+    }
+
+    #[test]
+    fn test_parse_decibel() {
+        assert_eq!(Ok(VolumeSpec::Decibel(-6.0)), "-6dB".parse());
+        assert_eq!(Ok(VolumeSpec::Decibel(3.5)), "3.5 db".parse());
+    }
+
+    #[test]
+    fn test_parse_linear_and_raw() {
+        assert_eq!(Ok(VolumeSpec::Linear(0.5)), "0.5x".parse());
+        assert_eq!(Ok(VolumeSpec::Raw(32768)), "32768raw".parse());
+    }
+
+    #[test]
+    fn test_parse_rejects_garbage() {
+        assert!("loud".parse::<VolumeSpec>().is_err());
+        assert!("-5".parse::<VolumeSpec>().is_err());
+        assert!("-0.5x".parse::<VolumeSpec>().is_err());
+        assert!("infx".parse::<VolumeSpec>().is_err());
+    }
+
+    #[test]
+    fn test_percent_is_linear_in_raw() {
+        assert_eq!(Volume::NORMAL, VolumeSpec::Percent(100.0).to_volume());
+        assert_eq!(Volume(32768), VolumeSpec::Percent(50.0).to_volume());
+    }
+
+    #[test]
+    fn test_percent_step_round_trips() {
+        let start = Volume(35457);
+        let step = VolumeSpec::Percent(3.3);
+
+        assert_eq!(start, step.lower(step.raise(start)));
+    }
+
+    #[test]
+    fn test_decibel_step() {
+        let start = VolumeSpec::Decibel(-12.0).to_volume();
+
+        let raised = VolumeSpec::Decibel(6.0).raise(start);
+
+        let db = VolumeDB::from(raised).0;
+        assert!((db + 6.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_steps_up_from_muted() {
+        let db = VolumeSpec::Decibel(3.0).raise(Volume::MUTED);
+        let factor = VolumeSpec::Linear(2.0).raise(Volume::MUTED);
+
+        assert!((VolumeDB::from(db).0 - (DECIBEL_FLOOR + 3.0)).abs() < 0.01);
+        assert!(factor > VolumeSpec::Decibel(DECIBEL_FLOOR).to_volume());
+        assert_eq!(
+            Volume::MUTED,
+            VolumeSpec::Decibel(-3.0).raise(Volume::MUTED)
+        );
+    }
+
+    #[test]
+    fn test_from_volume() {
+        let spec = VolumeSpec::from_volume(Volume::NORMAL, VolumeUnit::Percent);
+
+        assert_eq!("100%", spec.to_string());
+        assert_eq!(
+            "65536raw",
+            VolumeSpec::from_volume(Volume::NORMAL, VolumeUnit::Raw).to_string()
+        );
+    }
+}
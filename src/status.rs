@@ -0,0 +1,199 @@
+//! One line summaries of a device for status bars. The text comes from a template such as
+//! "{icon} {volume}% {description}" and is then wrapped in whatever the bar expects.
+use crate::pulse_wrappers::device::Device;
+use serde::Serialize;
+use std::str::FromStr;
+
+pub static DEFAULT_TEMPLATE: &str = "{icon} {volume}%";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatusFormat {
+    /// Just the template
+    #[default]
+    Plain,
+    /// waybar's custom module JSON protocol
+    Waybar,
+    /// i3blocks' full_text, short_text and color lines
+    I3blocks,
+    /// polybar's script module, muted devices are greyed out with a format tag
+    Polybar,
+}
+
+impl FromStr for StatusFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.to_ascii_lowercase().as_str() {
+            "plain" => Ok(StatusFormat::Plain),
+            "waybar" => Ok(StatusFormat::Waybar),
+            "i3blocks" => Ok(StatusFormat::I3blocks),
+            "polybar" => Ok(StatusFormat::Polybar),
+            _ => Err(format!(
+                "{format} is not a status format, try plain, waybar, i3blocks or polybar"
+            )),
+        }
+    }
+}
+
+/// Colour used for muted devices by the bars that support one
+static MUTED_COLOR: &str = "#7f7f7f";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Status {
+    pub volume: u8,
+    pub muted: bool,
+    pub name: String,
+    pub description: String,
+}
+
+#[derive(Serialize)]
+struct WaybarStatus<'a> {
+    text: &'a str,
+    tooltip: &'a str,
+    class: &'a str,
+    percentage: u8,
+}
+
+impl Status {
+    pub fn from_device<T>(device: &impl Device<T>) -> Status {
+        Status {
+            volume: device.get_volume_as_pct(),
+            muted: device.mute(),
+            name: device.name().to_string(),
+            description: device.description().to_string(),
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self.volume {
+            _ if self.muted => "🔇",
+            0 => "🔈",
+            1..=50 => "🔉",
+            _ => "🔊",
+        }
+    }
+
+    /// Fills in {icon}, {volume}, {muted}, {name} and {description}. This is done in one
+    /// pass, so a description with {volume} in it is left as it is. Anything else in braces
+    /// is kept too.
+    pub fn fill_template(&self, template: &str) -> String {
+        let mut filled = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            filled.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            let value = rest
+                .find('}')
+                .and_then(|end| Some((end, self.placeholder(&rest[1..end])?)));
+            match value {
+                Some((end, value)) => {
+                    filled.push_str(&value);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    filled.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        filled.push_str(rest);
+
+        filled
+    }
+
+    fn placeholder(&self, name: &str) -> Option<String> {
+        match name {
+            "icon" => Some(self.icon().to_string()),
+            "volume" => Some(self.volume.to_string()),
+            "muted" => Some(if self.muted { "muted" } else { "" }.to_string()),
+            "name" => Some(self.name.clone()),
+            "description" => Some(self.description.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn render(&self, format: StatusFormat, template: &str) -> String {
+        let text = self.fill_template(template);
+        let class = if self.muted { "muted" } else { "unmuted" };
+
+        match format {
+            StatusFormat::Plain => text,
+            StatusFormat::Waybar => serde_json::to_string(&WaybarStatus {
+                text: &text,
+                tooltip: &self.description,
+                class,
+                percentage: self.volume,
+            })
+            .unwrap_or_default(),
+            StatusFormat::I3blocks if self.muted => format!("{text}\n{text}\n{MUTED_COLOR}"),
+            StatusFormat::I3blocks => format!("{text}\n{text}"),
+            // polybar reads %{ as the start of a format tag, %% is a plain %
+            StatusFormat::Polybar if self.muted => {
+                format!("%{{F{MUTED_COLOR}}}{}%{{F-}}", text.replace('%', "%%"))
+            }
+            StatusFormat::Polybar => text.replace('%', "%%"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(muted: bool) -> Status {
+        Status {
+            volume: 54,
+            muted,
+            name: "alsa_output.test".to_string(),
+            description: "Test Speakers".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_fill_template() {
+        let text = status(false).fill_template("{icon} {volume}% {description}");
+
+        assert_eq!("🔊 54% Test Speakers", text);
+    }
+
+    #[test]
+    fn test_values_are_not_filled_in_again() {
+        let mut status = status(false);
+        status.description = "{volume} {name}".to_string();
+
+        let text = status.fill_template("{description} {nope} {{volume}");
+
+        assert_eq!("{volume} {name} {nope} {54", text);
+    }
+
+    #[test]
+    fn test_polybar_escapes_format_tags() {
+        let mut status = status(false);
+        status.description = "%{F#f00}Loud: 100%".to_string();
+
+        let polybar = status.render(StatusFormat::Polybar, "{volume}% {description}");
+
+        assert_eq!("54%% %%{F#f00}Loud: 100%%", polybar);
+    }
+
+    #[test]
+    fn test_waybar_is_json() {
+        let rendered = status(true).render(StatusFormat::Waybar, DEFAULT_TEMPLATE);
+        let value: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+
+        assert_eq!(value["text"], "🔇 54%");
+        assert_eq!(value["class"], "muted");
+        assert_eq!(value["percentage"], 54);
+    }
+
+    #[test]
+    fn test_muted_colour() {
+        let i3blocks = status(true).render(StatusFormat::I3blocks, DEFAULT_TEMPLATE);
+        let polybar = status(true).render(StatusFormat::Polybar, "{volume}");
+
+        assert_eq!(Some(MUTED_COLOR), i3blocks.lines().nth(2));
+        assert_eq!("%{F#7f7f7f}54%{F-}", polybar);
+    }
+}